@@ -23,8 +23,12 @@ impl Ord for NodeAndScore
 {
 	fn cmp(&self, other: &Self) -> std::cmp::Ordering
 	{
-		// Reverse to make the heap a minheap
-		self.f_score.partial_cmp(&other.f_score).unwrap().reverse()
+		// Reverse to make the heap a minheap. Ties are broken by position, to agree with `eq`.
+		self.f_score
+			.partial_cmp(&other.f_score)
+			.unwrap()
+			.reverse()
+			.then_with(|| (self.pos.x, self.pos.y).cmp(&(other.pos.x, other.pos.y)))
 	}
 }
 
@@ -40,7 +44,7 @@ impl PartialEq for NodeAndScore
 {
 	fn eq(&self, other: &Self) -> bool
 	{
-		self == other
+		self.pos == other.pos && self.f_score == other.f_score
 	}
 }
 
@@ -52,6 +56,8 @@ pub struct AStarContext
 	came_from: Vec<isize>,
 	cost: Vec<f32>,
 	size: usize,
+	expansions: usize,
+	path_cost: Option<f32>,
 }
 
 impl AStarContext
@@ -64,9 +70,23 @@ impl AStarContext
 			came_from: vec![-1; len],
 			cost: vec![0.; len],
 			size: size,
+			expansions: 0,
+			path_cost: None,
 		}
 	}
 
+	/// Total cost of the path found by the last search, or `None` if the goal was not reached.
+	pub fn path_cost(&self) -> Option<f32>
+	{
+		self.path_cost
+	}
+
+	/// Number of nodes popped from the open set during the last search.
+	pub fn expansions(&self) -> usize
+	{
+		self.expansions
+	}
+
 	fn heuristic(&self, from: Point2<i32>, to: Point2<i32>) -> f32
 	{
 		let dx = (from.x - to.x) as f32;
@@ -91,12 +111,100 @@ impl AStarContext
 		Point2::new((idx % self.size) as i32, (idx / self.size) as i32)
 	}
 
-	/// N.B. this returns the path in reverse order.
+	/// Returns the walkable cell on the map closest to `pos`, searching up to `max_radius` cells
+	/// away. Useful when a target lands on a solid cell or off the map, so that `solve` starts
+	/// and ends on valid cells.
+	pub fn nearest_walkable<S: Fn(Point2<i32>) -> bool>(
+		&self, pos: Point2<i32>, is_solid: S, max_radius: i32,
+	) -> Option<Point2<i32>>
+	{
+		let mut best: Option<(f32, Point2<i32>)> = None;
+		for r in 0..=max_radius
+		{
+			for y in pos.y - r..=pos.y + r
+			{
+				for x in pos.x - r..=pos.x + r
+				{
+					// Only the ring at distance r, the inside was checked already.
+					if (x - pos.x).abs() != r && (y - pos.y).abs() != r
+					{
+						continue;
+					}
+					let cand = Point2::new(x, y);
+					if self.map_to_idx(cand).is_none() || is_solid(cand)
+					{
+						continue;
+					}
+					let dist = self.heuristic(pos, cand);
+					if best.map(|(d, _)| dist < d).unwrap_or(true)
+					{
+						best = Some((dist, cand));
+					}
+				}
+			}
+			// Cells in further rings are at least r + 1 away.
+			if let Some((d, _)) = best
+			{
+				if d <= (r + 1) as f32
+				{
+					break;
+				}
+			}
+		}
+		best.map(|(_, p)| p)
+	}
+
+	/// Removes waypoints from a forward path whenever `line_of_sight_fn` reports a clear straight
+	/// segment past them. The first and last points are always kept.
+	pub fn smooth_path<L: Fn(Point2<i32>, Point2<i32>) -> bool>(
+		&self, path: &[Point2<i32>], line_of_sight_fn: L,
+	) -> Vec<Point2<i32>>
+	{
+		if path.len() < 3
+		{
+			return path.to_vec();
+		}
+		let mut ret = vec![path[0]];
+		for i in 1..path.len() - 1
+		{
+			if !line_of_sight_fn(*ret.last().unwrap(), path[i + 1])
+			{
+				ret.push(path[i]);
+			}
+		}
+		ret.push(*path.last().unwrap());
+		ret
+	}
+
+	/// Like `solve`, but returns the path ordered from `from` to `to`. Each point appears once.
+	pub fn solve_forward<S: Fn(Point2<i32>) -> bool, C: Fn(Point2<i32>) -> f32>(
+		&mut self, from: Point2<i32>, to: Point2<i32>, is_solid: S, cost_fn: C,
+	) -> Vec<Point2<i32>>
+	{
+		let mut path = self.solve(from, to, is_solid, cost_fn);
+		path.reverse();
+		path.dedup();
+		path
+	}
+
+	/// N.B. this returns the path in reverse order, from `to` back to `from`. See `solve_forward`.
 	pub fn solve<S: Fn(Point2<i32>) -> bool, C: Fn(Point2<i32>) -> f32>(
 		&mut self, from: Point2<i32>, to: Point2<i32>, is_solid: S, cost_fn: C,
 	) -> Vec<Point2<i32>>
+	{
+		self.solve_with_budget(from, to, is_solid, cost_fn, 0)
+	}
+
+	/// Like `solve`, but gives up after popping `max_expansions` nodes and returns the path to the
+	/// node closest to `to` found so far. A budget of 0 means unlimited.
+	pub fn solve_with_budget<S: Fn(Point2<i32>) -> bool, C: Fn(Point2<i32>) -> f32>(
+		&mut self, from: Point2<i32>, to: Point2<i32>, is_solid: S, cost_fn: C,
+		max_expansions: usize,
+	) -> Vec<Point2<i32>>
 	{
 		self.open_set.clear();
+		self.expansions = 0;
+		self.path_cost = None;
 		for i in 0..self.came_from.len()
 		{
 			self.came_from[i] = -1;
@@ -115,11 +223,17 @@ impl AStarContext
 		let to_idx = self.map_to_idx(to).unwrap();
 		while !self.open_set.is_empty()
 		{
+			if max_expansions > 0 && self.expansions >= max_expansions
+			{
+				break;
+			}
 			let cur = self.open_set.pop().unwrap();
+			self.expansions += 1;
 			//~ println!("Trying {:?}", cur);
 			let cur_idx = self.map_to_idx(cur.pos).unwrap();
 			if cur_idx == to_idx
 			{
+				self.path_cost = Some(self.cost[to_idx]);
 				let mut cur_idx = to_idx;
 				let mut path = vec![to];
 				//~ println!("Start {:?} {:?}", from, to);
@@ -191,3 +305,145 @@ impl AStarContext
 		}
 	}
 }
+
+#[test]
+fn test_high_cost_detour()
+{
+	let mut ctx = AStarContext::new(5);
+	let from = Point2::new(0, 2);
+	let to = Point2::new(4, 2);
+	// Column 2 is expensive, except for the top cell.
+	let path = ctx.solve(
+		from,
+		to,
+		|_| false,
+		|pos| if pos.x == 2 && pos.y != 0 { 100. } else { 0. },
+	);
+	assert_eq!(path.first(), Some(&to));
+	assert_eq!(path.last(), Some(&from));
+	assert!(path.contains(&Point2::new(2, 0)));
+	assert!(!path.contains(&Point2::new(2, 2)));
+}
+
+#[test]
+fn test_solve_forward()
+{
+	let mut ctx = AStarContext::new(5);
+	let from = Point2::new(0, 0);
+	let to = Point2::new(3, 0);
+	let path = ctx.solve_forward(from, to, |_| false, |_| 0.);
+	assert_eq!(
+		path,
+		vec![
+			Point2::new(0, 0),
+			Point2::new(1, 0),
+			Point2::new(2, 0),
+			Point2::new(3, 0)
+		]
+	);
+
+	// The reverse path would contain the start point twice here.
+	let path = ctx.solve_forward(from, from, |_| false, |_| 0.);
+	assert_eq!(path, vec![from]);
+}
+
+#[test]
+fn test_smooth_path()
+{
+	let ctx = AStarContext::new(5);
+	let path = vec![
+		Point2::new(0, 0),
+		Point2::new(1, 0),
+		Point2::new(1, 1),
+		Point2::new(2, 1),
+		Point2::new(2, 2),
+	];
+	let smoothed = ctx.smooth_path(&path, |_, _| true);
+	assert_eq!(smoothed, vec![Point2::new(0, 0), Point2::new(2, 2)]);
+
+	// Only axis-aligned segments are visible, so the corners must be kept.
+	let smoothed = ctx.smooth_path(&path, |a, b| a.x == b.x || a.y == b.y);
+	assert_eq!(smoothed, path);
+}
+
+#[test]
+fn test_path_stats()
+{
+	let mut ctx = AStarContext::new(5);
+	ctx.solve(Point2::new(0, 0), Point2::new(3, 0), |_| false, |_| 0.5);
+	assert_eq!(ctx.path_cost(), Some(4.5));
+	assert!(ctx.expansions() >= 4);
+
+	// The goal is walled off.
+	ctx.solve(
+		Point2::new(0, 0),
+		Point2::new(4, 4),
+		|pos| pos.x == 2,
+		|_| 0.,
+	);
+	assert_eq!(ctx.path_cost(), None);
+	assert_eq!(ctx.expansions(), 10);
+}
+
+#[test]
+fn test_solve_with_budget()
+{
+	let mut ctx = AStarContext::new(10);
+	let from = Point2::new(0, 0);
+	let to = Point2::new(9, 0);
+	let path = ctx.solve_with_budget(from, to, |_| false, |_| 0., 3);
+	assert_eq!(ctx.expansions(), 3);
+	assert_eq!(ctx.path_cost(), None);
+	assert_eq!(path.last(), Some(&from));
+	assert!(!path.contains(&to));
+
+	let path = ctx.solve_with_budget(from, to, |_| false, |_| 0., 0);
+	assert_eq!(path.first(), Some(&to));
+}
+
+#[test]
+fn test_node_and_score_eq()
+{
+	let a = NodeAndScore::new(Point2::new(1, 2), 3.);
+	let b = NodeAndScore::new(Point2::new(1, 2), 3.);
+	let c = NodeAndScore::new(Point2::new(2, 1), 3.);
+	let d = NodeAndScore::new(Point2::new(1, 2), 4.);
+	assert!(a == b);
+	assert!(a != c);
+	assert!(a != d);
+}
+
+#[test]
+fn test_nearest_walkable()
+{
+	let ctx = AStarContext::new(8);
+	// Everything left of x = 5 is solid.
+	let is_solid = |p: Point2<i32>| p.x < 5;
+	assert_eq!(
+		ctx.nearest_walkable(Point2::new(6, 3), is_solid, 4),
+		Some(Point2::new(6, 3))
+	);
+	assert_eq!(
+		ctx.nearest_walkable(Point2::new(3, 3), is_solid, 4),
+		Some(Point2::new(5, 3))
+	);
+	// Off the map.
+	assert_eq!(
+		ctx.nearest_walkable(Point2::new(10, 3), is_solid, 4),
+		Some(Point2::new(7, 3))
+	);
+	assert_eq!(ctx.nearest_walkable(Point2::new(0, 3), is_solid, 4), None);
+}
+
+#[test]
+fn test_node_and_score_ord()
+{
+	let a = NodeAndScore::new(Point2::new(1, 2), 3.);
+	let b = NodeAndScore::new(Point2::new(2, 1), 3.);
+	let c = NodeAndScore::new(Point2::new(0, 0), 4.);
+	assert_ne!(a.cmp(&b), std::cmp::Ordering::Equal);
+	assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+	assert_eq!(a.cmp(&a), std::cmp::Ordering::Equal);
+	// Lower scores come out of the heap first.
+	assert!(a > c && b > c);
+}
@@ -32,17 +32,84 @@ pub struct Page
 {
 	pub bitmap: Bitmap,
 	packer: rect_packer::Packer,
+	/// Regions released by `Atlas::remove`.
+	free: Vec<rect_packer::Rect>,
+}
+
+/// Takes a w x h region out of the smallest free rectangle that fits it, splitting off the
+/// remainder. Free rectangles are never merged, so heavy churn of differently sized bitmaps will
+/// fragment the free space.
+fn take_free_rect(free: &mut Vec<rect_packer::Rect>, w: i32, h: i32) -> Option<rect_packer::Rect>
+{
+	let (idx, _) = free
+		.iter()
+		.enumerate()
+		.filter(|(_, r)| r.width >= w && r.height >= h)
+		.min_by_key(|(_, r)| r.area())?;
+	let rect = free.swap_remove(idx);
+	if rect.width > w
+	{
+		free.push(rect_packer::Rect::new(
+			rect.x + w,
+			rect.y,
+			rect.width - w,
+			h,
+		));
+	}
+	if rect.height > h
+	{
+		free.push(rect_packer::Rect::new(
+			rect.x,
+			rect.y + h,
+			rect.width,
+			rect.height - h,
+		));
+	}
+	Some(rect_packer::Rect::new(rect.x, rect.y, w, h))
+}
+
+/// The region `atlas_bitmap` takes up on its page, including the padding around it.
+fn padded_rect(atlas_bitmap: &AtlasBitmap, padding: i32) -> rect_packer::Rect
+{
+	rect_packer::Rect::new(
+		atlas_bitmap.start.x as i32 - padding,
+		atlas_bitmap.start.y as i32 - padding,
+		atlas_bitmap.width() as i32 + 2 * padding,
+		atlas_bitmap.height() as i32 + 2 * padding,
+	)
+}
+
+/// Adds `rect` to the free rectangles, failing if any part of it is already free.
+fn release_rect(free: &mut Vec<rect_packer::Rect>, rect: rect_packer::Rect) -> Result<()>
+{
+	let overlaps = |r: &rect_packer::Rect| {
+		r.left() < rect.right()
+			&& rect.left() < r.right()
+			&& r.top() < rect.bottom()
+			&& rect.top() < r.bottom()
+	};
+	if free.iter().any(overlaps)
+	{
+		return Err(format!(
+			"Region {}x{} at ({}, {}) was already removed",
+			rect.width, rect.height, rect.x, rect.y
+		)
+		.into());
+	}
+	free.push(rect);
+	Ok(())
 }
 
 impl Page
 {
 	fn new(core: &Core, size: i32) -> Result<Self>
 	{
+		// Padding is handled by Page::insert.
 		let config = rect_packer::Config {
 			width: size,
 			height: size,
-			border_padding: 1,
-			rectangle_padding: 1,
+			border_padding: 0,
+			rectangle_padding: 0,
 		};
 
 		let bitmap = Bitmap::new(core, size, size)
@@ -55,26 +122,88 @@ impl Page
 		Ok(Page {
 			bitmap: bitmap,
 			packer: rect_packer::Packer::new(config),
+			free: vec![],
 		})
 	}
 
-	fn insert<B: BitmapLike>(&mut self, core: &Core, bitmap: &B, page: usize)
-		-> Option<AtlasBitmap>
+	fn insert<B: BitmapLike>(
+		&mut self, core: &Core, bitmap: &B, page: usize, padding: i32, extrude: bool,
+	) -> Option<AtlasBitmap>
 	{
-		if let Some(placement) = self
-			.packer
-			.pack(bitmap.get_width(), bitmap.get_height(), false)
+		let w = bitmap.get_width();
+		let h = bitmap.get_height();
+		let (pw, ph) = (w + 2 * padding, h + 2 * padding);
+		let reused = take_free_rect(&mut self.free, pw, ph);
+		let is_reused = reused.is_some();
+		if let Some(placement) = reused.or_else(|| self.packer.pack(pw, ph, false))
 		{
+			let x = placement.x + padding;
+			let y = placement.y + padding;
 			core.set_target_bitmap(Some(&self.bitmap));
+			if is_reused
+			{
+				// Don't let the removed bitmap show through the transparent parts of this one.
+				core.set_clipping_rectangle(
+					placement.x,
+					placement.y,
+					placement.width,
+					placement.height,
+				);
+				core.clear_to_color(Color::from_rgba_f(0., 0., 0., 0.));
+				core.reset_clipping_rectangle();
+			}
 			core.set_blender(BlendOperation::Add, BlendMode::One, BlendMode::Zero);
-			core.draw_bitmap(bitmap, placement.x as f32, placement.y as f32, Flag::zero());
+			core.draw_bitmap(bitmap, x as f32, y as f32, Flag::zero());
+			if extrude
+			{
+				// Copy the edge pixels into the gutter, so that filtering near the edges doesn't
+				// pick up the neighbours.
+				let (wf, hf) = (w as f32, h as f32);
+				let (xf, yf) = (x as f32, y as f32);
+				for i in 1..=padding
+				{
+					let i = i as f32;
+					core.draw_bitmap_region(bitmap, 0., 0., 1., hf, xf - i, yf, Flag::zero());
+					core.draw_bitmap_region(
+						bitmap,
+						wf - 1.,
+						0.,
+						1.,
+						hf,
+						xf + wf - 1. + i,
+						yf,
+						Flag::zero(),
+					);
+					core.draw_bitmap_region(bitmap, 0., 0., wf, 1., xf, yf - i, Flag::zero());
+					core.draw_bitmap_region(
+						bitmap,
+						0.,
+						hf - 1.,
+						wf,
+						1.,
+						xf,
+						yf + hf - 1. + i,
+						Flag::zero(),
+					);
+					for j in 1..=padding
+					{
+						let j = j as f32;
+						for (sx, sy, dx, dy) in [
+							(0., 0., xf - i, yf - j),
+							(wf - 1., 0., xf + wf - 1. + i, yf - j),
+							(0., hf - 1., xf - i, yf + hf - 1. + j),
+							(wf - 1., hf - 1., xf + wf - 1. + i, yf + hf - 1. + j),
+						]
+						{
+							core.draw_bitmap_region(bitmap, sx, sy, 1., 1., dx, dy, Flag::zero());
+						}
+					}
+				}
+			}
 			core.set_blender(BlendOperation::Add, BlendMode::One, BlendMode::InverseAlpha);
 			Some(AtlasBitmap {
-				start: Point2::new(placement.x as f32, placement.y as f32),
-				end: Point2::new(
-					(placement.x + placement.width) as f32,
-					(placement.y + placement.height) as f32,
-				),
+				start: Point2::new(x as f32, y as f32),
+				end: Point2::new((x + w) as f32, (y + h) as f32),
 				page: page,
 			})
 		}
@@ -90,24 +219,41 @@ pub struct Atlas
 	pub pages: Vec<Page>,
 	bitmaps: HashMap<String, AtlasBitmap>,
 	page_size: i32,
+	padding: i32,
+	extrude: bool,
 }
 
 impl Atlas
 {
 	pub fn new(page_size: i32) -> Self
+	{
+		Self::with_padding(page_size, 1, false)
+	}
+
+	/// Creates an atlas that leaves `padding` pixels around each bitmap. If `extrude` is set, the
+	/// padding is filled with copies of the bitmap's edge pixels, which avoids bleeding when the
+	/// bitmaps are drawn scaled or with filtering.
+	pub fn with_padding(page_size: i32, padding: i32, extrude: bool) -> Self
 	{
 		Self {
 			pages: vec![],
 			bitmaps: HashMap::new(),
 			page_size: page_size,
+			padding: padding,
+			extrude: extrude,
 		}
 	}
 
+	pub fn page_size(&self) -> i32
+	{
+		self.page_size
+	}
+
 	pub fn insert<B: BitmapLike>(&mut self, core: &Core, bitmap: &B) -> Result<AtlasBitmap>
 	{
 		for (id, page) in self.pages.iter_mut().enumerate()
 		{
-			if let Some(atlas_bitmap) = page.insert(core, bitmap, id)
+			if let Some(atlas_bitmap) = page.insert(core, bitmap, id, self.padding, self.extrude)
 			{
 				return Ok(atlas_bitmap);
 			}
@@ -115,7 +261,11 @@ impl Atlas
 
 		self.pages.push(Page::new(core, self.page_size)?);
 		let id = self.pages.len() - 1;
-		if let Some(atlas_bitmap) = self.pages.last_mut().unwrap().insert(core, bitmap, id)
+		if let Some(atlas_bitmap) =
+			self.pages
+				.last_mut()
+				.unwrap()
+				.insert(core, bitmap, id, self.padding, self.extrude)
 		{
 			return Ok(atlas_bitmap);
 		}
@@ -130,6 +280,25 @@ impl Atlas
 		}
 	}
 
+	/// Releases the space used by `atlas_bitmap` so later insertions can reuse it. The bitmap
+	/// must have been inserted with this atlas, and must not be used afterwards. Removing the
+	/// same bitmap twice is an error.
+	pub fn remove(&mut self, atlas_bitmap: &AtlasBitmap) -> Result<()>
+	{
+		let page = self
+			.pages
+			.get_mut(atlas_bitmap.page)
+			.ok_or_else(|| format!("Atlas has no page {}", atlas_bitmap.page))?;
+		release_rect(&mut page.free, padded_rect(atlas_bitmap, self.padding))
+	}
+
+	/// Removes all pages. All previously returned `AtlasBitmap`s become invalid.
+	pub fn clear(&mut self)
+	{
+		self.pages.clear();
+		self.bitmaps.clear();
+	}
+
 	pub fn insert_from_file(&mut self, core: &Core, filename: &str) -> Result<AtlasBitmap>
 	{
 		let old_flags = core.get_new_bitmap_flags();
@@ -153,3 +322,49 @@ impl Atlas
 		}
 	}
 }
+
+#[test]
+fn test_free_rect_reuse()
+{
+	let mut free = vec![];
+	assert!(take_free_rect(&mut free, 4, 4).is_none());
+
+	// A removed 10x10 region is reused by an equally sized bitmap.
+	free.push(rect_packer::Rect::new(20, 30, 10, 10));
+	let rect = take_free_rect(&mut free, 10, 10).unwrap();
+	assert_eq!((rect.x, rect.y, rect.width, rect.height), (20, 30, 10, 10));
+	assert!(free.is_empty());
+
+	// Smaller bitmaps split the region.
+	free.push(rect_packer::Rect::new(0, 0, 10, 10));
+	let rect = take_free_rect(&mut free, 4, 6).unwrap();
+	assert_eq!((rect.x, rect.y), (0, 0));
+	assert_eq!(free.len(), 2);
+	assert!(take_free_rect(&mut free, 6, 6).is_some());
+	assert!(take_free_rect(&mut free, 10, 4).is_some());
+	assert!(free.is_empty());
+}
+
+#[test]
+fn test_remove_rect()
+{
+	let atlas_bitmap = AtlasBitmap {
+		start: Point2::new(12., 22.),
+		end: Point2::new(20., 26.),
+		page: 0,
+	};
+	let rect = padded_rect(&atlas_bitmap, 2);
+	assert_eq!((rect.x, rect.y, rect.width, rect.height), (10, 20, 12, 8));
+	let rect = padded_rect(&atlas_bitmap, 0);
+	assert_eq!((rect.x, rect.y, rect.width, rect.height), (12, 22, 8, 4));
+
+	let mut free = vec![];
+	release_rect(&mut free, padded_rect(&atlas_bitmap, 2)).unwrap();
+	assert!(release_rect(&mut free, padded_rect(&atlas_bitmap, 2)).is_err());
+	// Still rejected after part of it got reused.
+	take_free_rect(&mut free, 4, 4).unwrap();
+	assert!(release_rect(&mut free, padded_rect(&atlas_bitmap, 2)).is_err());
+	// Adjacent regions are fine.
+	release_rect(&mut free, rect_packer::Rect::new(22, 20, 4, 4)).unwrap();
+	assert_eq!(free.len(), 3);
+}
@@ -1,4 +1,5 @@
 use crate::sprite;
+use crate::utils::PI;
 use allegro::*;
 use na::Point2;
 use nalgebra as na;
@@ -28,3 +29,70 @@ impl Position
 		self.pos + alpha * (self.pos - self.old_pos)
 	}
 }
+
+/// A 2D camera that's snapshotted every logic tick like `Position`.
+///
+/// Gameplay should read and write `pos`/`angle` directly, while drawing should
+/// go through `draw_transform` so the view moves smoothly between ticks.
+#[derive(Debug, Copy, Clone)]
+pub struct Camera
+{
+	pub pos: Point2<f32>,
+	old_pos: Point2<f32>,
+	pub angle: f32,
+	old_angle: f32,
+}
+
+impl Camera
+{
+	pub fn new(pos: Point2<f32>, angle: f32) -> Self
+	{
+		Self {
+			pos: pos,
+			old_pos: pos,
+			angle: angle,
+			old_angle: angle,
+		}
+	}
+
+	/// Call at the start of every logic tick, before moving the camera.
+	pub fn snapshot(&mut self)
+	{
+		self.old_pos = self.pos;
+		self.old_angle = self.angle;
+	}
+
+	pub fn draw_pos(&self, alpha: f32) -> Point2<f32>
+	{
+		self.pos + alpha * (self.pos - self.old_pos)
+	}
+
+	/// Like `draw_pos`, going the short way around the circle.
+	pub fn draw_angle(&self, alpha: f32) -> f32
+	{
+		let diff = (self.angle - self.old_angle + PI).rem_euclid(2. * PI) - PI;
+		self.angle + alpha * diff
+	}
+
+	/// Transform that puts the camera at the center of a `width` x `height` buffer.
+	pub fn draw_transform(&self, alpha: f32, width: f32, height: f32) -> Transform
+	{
+		let pos = self.draw_pos(alpha);
+		let mut transform = Transform::identity();
+		transform.translate(-pos.x, -pos.y);
+		transform.rotate(-self.draw_angle(alpha));
+		transform.translate(width / 2., height / 2.);
+		transform
+	}
+}
+
+#[test]
+fn test_camera_draw_angle()
+{
+	let mut camera = Camera::new(Point2::new(0., 0.), PI - 0.1);
+	camera.snapshot();
+	camera.angle = -PI + 0.1;
+	assert!((camera.draw_angle(0.5) - (-PI + 0.2)).abs() < 1e-5);
+	camera.snapshot();
+	assert_eq!(camera.draw_angle(0.5), camera.angle);
+}
@@ -1,8 +1,10 @@
 use serde_derive::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::fmt;
 
+use crate::error;
 use crate::utils;
 
 #[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Copy, Clone, Debug, PartialOrd, Ord)]
@@ -33,14 +35,39 @@ pub enum Input
 	MouseXNeg,
 	MouseYNeg,
 	MouseZNeg,
+	/// The second key pressed while the first (usually a modifier) is held.
+	Chord(allegro::KeyCode, allegro::KeyCode),
+}
+
+/// Keys that start a chord when captured via `ControlsHandler::capture_next_input`.
+fn is_modifier(keycode: allegro::KeyCode) -> bool
+{
+	matches!(
+		keycode,
+		allegro::KeyCode::LShift
+			| allegro::KeyCode::RShift
+			| allegro::KeyCode::LCtrl
+			| allegro::KeyCode::RCtrl
+			| allegro::KeyCode::Alt
+			| allegro::KeyCode::AltGr
+	)
 }
 
 impl Input
 {
-	pub fn to_str(&self) -> &'static str
+	pub fn to_str(&self) -> Cow<'static, str>
 	{
-		match self
+		let s = match self
 		{
+			Input::Chord(modifier, key) =>
+			{
+				return format!(
+					"{}+{}",
+					Input::Keyboard(*modifier).to_str(),
+					Input::Keyboard(*key).to_str()
+				)
+				.into();
+			}
 			Input::Keyboard(k) => match k
 			{
 				allegro::KeyCode::A => "A",
@@ -192,11 +219,20 @@ impl Input
 			Input::MouseXPos => "MouseX+",
 			Input::MouseYPos => "MouseY+",
 			Input::MouseZPos => "MouseZ+",
-		}
+		};
+		s.into()
 	}
 
 	pub fn from_str(s: &str) -> Option<Self>
 	{
+		if let Some((modifier, key)) = s.split_once('+')
+		{
+			if let (Some(Input::Keyboard(modifier)), Some(Input::Keyboard(key))) =
+				(Input::from_str(modifier), Input::from_str(key))
+			{
+				return Some(Input::Chord(modifier, key));
+			}
+		}
 		let mut input = match s
 		{
 			"A" => Some(allegro::KeyCode::A),
@@ -368,13 +404,95 @@ impl Input
 	}
 }
 
+/// Human-readable names, for the UI. See `Input::to_str` for the names used in config files.
+impl fmt::Display for Input
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		use allegro::KeyCode;
+		let name = match self
+		{
+			Input::Keyboard(k) => match k
+			{
+				KeyCode::Up => "Arrow Up",
+				KeyCode::Down => "Arrow Down",
+				KeyCode::Left => "Arrow Left",
+				KeyCode::Right => "Arrow Right",
+				KeyCode::PgUp => "Page Up",
+				KeyCode::PgDn => "Page Down",
+				KeyCode::LShift => "Left Shift",
+				KeyCode::RShift => "Right Shift",
+				KeyCode::LCtrl => "Left Ctrl",
+				KeyCode::RCtrl => "Right Ctrl",
+				KeyCode::AltGr => "Alt Gr",
+				KeyCode::LWin => "Left Win",
+				KeyCode::RWin => "Right Win",
+				KeyCode::Tilde => "~",
+				KeyCode::Minus => "-",
+				KeyCode::Equals => "=",
+				KeyCode::Openbrace => "[",
+				KeyCode::Closebrace => "]",
+				KeyCode::Semicolon => ";",
+				KeyCode::Quote => "'",
+				KeyCode::Backslash => "\\",
+				KeyCode::Comma => ",",
+				KeyCode::Fullstop => ".",
+				KeyCode::Slash => "/",
+				KeyCode::Pad0 => "Pad 0",
+				KeyCode::Pad1 => "Pad 1",
+				KeyCode::Pad2 => "Pad 2",
+				KeyCode::Pad3 => "Pad 3",
+				KeyCode::Pad4 => "Pad 4",
+				KeyCode::Pad5 => "Pad 5",
+				KeyCode::Pad6 => "Pad 6",
+				KeyCode::Pad7 => "Pad 7",
+				KeyCode::Pad8 => "Pad 8",
+				KeyCode::Pad9 => "Pad 9",
+				KeyCode::PadSlash => "Pad /",
+				KeyCode::PadAsterisk => "Pad *",
+				KeyCode::PadMinus => "Pad -",
+				KeyCode::PadPlus => "Pad +",
+				KeyCode::PadDelete => "Pad Delete",
+				KeyCode::PadEnter => "Pad Enter",
+				KeyCode::PadEquals => "Pad =",
+				KeyCode::PrintScreen => "Print Screen",
+				KeyCode::ScrollLock => "Scroll Lock",
+				KeyCode::NumLock => "Num Lock",
+				KeyCode::CapsLock => "Caps Lock",
+				_ => return write!(f, "{}", self.to_str()),
+			},
+			Input::MouseButton(b) => match b
+			{
+				1 | 2 | 3 => return write!(f, "{}", self.to_str()),
+				b => return write!(f, "Mouse {}", b),
+			},
+			Input::Chord(modifier, key) =>
+			{
+				return write!(
+					f,
+					"{}+{}",
+					Input::Keyboard(*modifier),
+					Input::Keyboard(*key)
+				);
+			}
+			Input::MouseXNeg => "Mouse Move Left",
+			Input::MouseXPos => "Mouse Move Right",
+			Input::MouseYNeg => "Mouse Move Up",
+			Input::MouseYPos => "Mouse Move Down",
+			Input::MouseZNeg => "Mouse Wheel Down",
+			Input::MouseZPos => "Mouse Wheel Up",
+		};
+		write!(f, "{}", name)
+	}
+}
+
 impl serde::Serialize for Input
 {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
 		S: serde::Serializer,
 	{
-		serializer.serialize_str(self.to_str())
+		serializer.serialize_str(&self.to_str())
 	}
 }
 
@@ -458,6 +576,12 @@ impl InputState
 		}
 	}
 
+	/// The strength once the queued events are applied, without consuming them.
+	fn peek(&self) -> f32
+	{
+		self.queue.last().copied().unwrap_or(self.strength)
+	}
+
 	fn clear(&mut self)
 	{
 		self.strength = 0.;
@@ -465,11 +589,16 @@ impl InputState
 	}
 }
 
+pub const DEFAULT_PRESS_THRESHOLD: f32 = 0.5;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Controls
 {
 	action_to_inputs: BTreeMap<Action, [Option<Input>; 2]>,
 	mouse_sensitivity: f32,
+	/// Overrides DEFAULT_PRESS_THRESHOLD for specific actions.
+	#[serde(default)]
+	press_thresholds: BTreeMap<Action, f32>,
 }
 
 impl Controls
@@ -485,9 +614,18 @@ impl Controls
 		Self {
 			action_to_inputs: action_to_inputs,
 			mouse_sensitivity: 0.1,
+			press_thresholds: BTreeMap::new(),
 		}
 	}
 
+	pub fn get_press_threshold(&self, action: Action) -> f32
+	{
+		self.press_thresholds
+			.get(&action)
+			.copied()
+			.unwrap_or(DEFAULT_PRESS_THRESHOLD)
+	}
+
 	pub fn get_action_string(&self, action: Action) -> String
 	{
 		let mut inputs = vec![];
@@ -495,7 +633,7 @@ impl Controls
 		{
 			if let Some(input) = input
 			{
-				inputs.push(input.to_str());
+				inputs.push(input.to_string());
 			}
 		}
 		inputs.join("/")
@@ -508,6 +646,57 @@ pub struct ControlsHandler
 	controls: Controls,
 	input_to_action: BTreeMap<Input, Action>,
 	input_state: HashMap<Input, InputState>,
+	consumed: BTreeSet<Action>,
+	capturing: bool,
+	captured_input: Option<Input>,
+	held_keys: BTreeSet<allegro::KeyCode>,
+	/// Actions that were pressed at the end of the previous tick.
+	prev_pressed: BTreeSet<Action>,
+	/// Cached `is_pressed` results for the current tick, see `action_pressed`.
+	tick_pressed: BTreeMap<Action, bool>,
+}
+
+fn event_is_escape(event: &allegro::Event) -> bool
+{
+	matches!(
+		event,
+		allegro::Event::KeyDown {
+			keycode: allegro::KeyCode::Escape,
+			..
+		}
+	)
+}
+
+/// Converts an event to the input that would be bound by it. Returns `None` if the event can't be
+/// bound, and `Some(None)` if it clears the binding.
+fn event_to_input(event: &allegro::Event) -> Option<Option<Input>>
+{
+	match event
+	{
+		allegro::Event::KeyDown { keycode, .. } => match *keycode
+		{
+			allegro::KeyCode::Escape => Some(None),
+			keycode => Some(Some(Input::Keyboard(keycode))),
+		},
+		allegro::Event::MouseButtonUp { button, .. } =>
+		{
+			Some(Some(Input::MouseButton(*button as i32)))
+		}
+		allegro::Event::MouseAxes { dx, dy, dz, .. } =>
+		{
+			Some(match (dx.cmp(&0), dy.cmp(&0), dz.cmp(&0))
+			{
+				(Ordering::Less, _, _) => Some(Input::MouseXNeg),
+				(Ordering::Greater, _, _) => Some(Input::MouseXPos),
+				(_, Ordering::Less, _) => Some(Input::MouseYNeg),
+				(_, Ordering::Greater, _) => Some(Input::MouseYPos),
+				(_, _, Ordering::Less) => Some(Input::MouseZNeg),
+				(_, _, Ordering::Greater) => Some(Input::MouseZPos),
+				_ => None,
+			})
+		}
+		_ => None,
+	}
 }
 
 impl ControlsHandler
@@ -518,6 +707,12 @@ impl ControlsHandler
 			controls: controls,
 			input_to_action: BTreeMap::new(),
 			input_state: HashMap::new(),
+			consumed: BTreeSet::new(),
+			capturing: false,
+			captured_input: None,
+			held_keys: BTreeSet::new(),
+			prev_pressed: BTreeSet::new(),
+			tick_pressed: BTreeMap::new(),
 		};
 		ret.update_derived();
 		ret
@@ -565,6 +760,16 @@ impl ControlsHandler
 		self.controls.mouse_sensitivity = mouse_sensitivity;
 	}
 
+	pub fn get_press_threshold(&self, action: Action) -> f32
+	{
+		self.controls.get_press_threshold(action)
+	}
+
+	pub fn set_press_threshold(&mut self, action: Action, threshold: f32)
+	{
+		self.controls.press_thresholds.insert(action, threshold);
+	}
+
 	pub fn get_actions_to_inputs(&self) -> impl Iterator<Item = (&Action, &[Option<Input>; 2])>
 	{
 		self.controls.action_to_inputs.iter()
@@ -575,40 +780,190 @@ impl ControlsHandler
 		self.controls.action_to_inputs.get(&action)
 	}
 
-	pub fn decode_event(&mut self, event: &allegro::Event) -> Vec<(f32, Action)>
+	/// Makes the next bindable event be captured as an input instead of affecting any action
+	/// states. Retrieve it with `take_captured_input`. Escape cancels the capture. Pressing a key
+	/// while holding a modifier captures a chord, releasing the modifier alone captures it by
+	/// itself.
+	pub fn capture_next_input(&mut self)
 	{
-		match event
+		self.capturing = true;
+		self.captured_input = None;
+	}
+
+	pub fn is_capturing(&self) -> bool
+	{
+		self.capturing
+	}
+
+	pub fn take_captured_input(&mut self) -> Option<Input>
+	{
+		self.captured_input.take()
+	}
+
+	/// Binds `input` to the `index`th slot of `action`. If another slot already uses `input`, that
+	/// slot is cleared and returned so the caller can warn about the conflict.
+	pub fn set_binding(
+		&mut self, action: Action, index: usize, input: Option<Input>,
+	) -> error::Result<Option<(Action, usize)>>
+	{
+		let num_slots = self
+			.controls
+			.action_to_inputs
+			.get(&action)
+			.ok_or_else(|| format!("No bindings for {:?}", action))?
+			.len();
+		if index >= num_slots
 		{
-			allegro::Event::KeyDown { keycode, .. } =>
+			return Err(format!(
+				"Binding slot {} out of range for {:?} (it has {})",
+				index, action, num_slots
+			)
+			.into());
+		}
+		let mut conflict = None;
+		if input.is_some()
+		{
+			for (&other_action, other_inputs) in self.controls.action_to_inputs.iter_mut()
 			{
-				if let Some(state) = self.input_state.get_mut(&Input::Keyboard(*keycode))
+				for (other_index, other_input) in other_inputs.iter_mut().enumerate()
 				{
-					state.push(1.);
+					if (other_action, other_index) != (action, index) && *other_input == input
+					{
+						*other_input = None;
+						conflict = Some((other_action, other_index));
+					}
 				}
 			}
+		}
+		self.controls.action_to_inputs.get_mut(&action).unwrap()[index] = input;
+		self.update_derived();
+		Ok(conflict)
+	}
+
+	/// Releases the input for a key or mouse button up event.
+	fn decode_release(&mut self, event: &allegro::Event)
+	{
+		match event
+		{
 			allegro::Event::KeyUp { keycode, .. } =>
 			{
+				for (input, state) in self.input_state.iter_mut()
+				{
+					if let Input::Chord(modifier, key) = input
+					{
+						if key == keycode || modifier == keycode
+						{
+							state.push(0.);
+						}
+					}
+				}
 				if let Some(state) = self.input_state.get_mut(&Input::Keyboard(*keycode))
 				{
 					state.push(0.);
 				}
 			}
-			allegro::Event::MouseButtonDown { button, .. } =>
+			allegro::Event::MouseButtonUp { button, .. } =>
 			{
 				if let Some(state) = self
 					.input_state
 					.get_mut(&Input::MouseButton(*button as i32))
 				{
-					state.push(1.);
+					state.push(0.);
 				}
 			}
-			allegro::Event::MouseButtonUp { button, .. } =>
+			_ => (),
+		}
+	}
+
+	pub fn decode_event(&mut self, event: &allegro::Event) -> Vec<(f32, Action)>
+	{
+		// Keys that were held before this event.
+		let held_keys = self.held_keys.clone();
+		match event
+		{
+			allegro::Event::KeyDown { keycode, .. } =>
+			{
+				self.held_keys.insert(*keycode);
+			}
+			allegro::Event::KeyUp { keycode, .. } =>
+			{
+				self.held_keys.remove(keycode);
+			}
+			_ => (),
+		}
+		if self.capturing
+		{
+			// Inputs held when capturing started still need to be released.
+			self.decode_release(event);
+			let held_modifier = held_keys.iter().copied().find(|k| is_modifier(*k));
+			match event
+			{
+				allegro::Event::KeyDown { keycode, .. } if is_modifier(*keycode) =>
+				{
+					return vec![];
+				}
+				allegro::Event::KeyDown { keycode, .. }
+					if held_modifier.is_some() && !event_is_escape(event) =>
+				{
+					self.capturing = false;
+					self.captured_input = Some(Input::Chord(held_modifier.unwrap(), *keycode));
+					return vec![];
+				}
+				allegro::Event::KeyUp { keycode, .. } if is_modifier(*keycode) =>
+				{
+					self.capturing = false;
+					self.captured_input = Some(Input::Keyboard(*keycode));
+					return vec![];
+				}
+				_ => (),
+			}
+			if let Some(input) = event_to_input(event)
+			{
+				// Ignore stray mouse motion without any movement.
+				if input.is_some() || event_is_escape(event)
+				{
+					self.capturing = false;
+					self.captured_input = input;
+				}
+			}
+			return vec![];
+		}
+		match event
+		{
+			allegro::Event::KeyDown { keycode, .. } =>
+			{
+				// A completed chord takes over its key, so the key's own action doesn't fire.
+				let mut chorded = false;
+				for (input, state) in self.input_state.iter_mut()
+				{
+					if let Input::Chord(modifier, key) = input
+					{
+						if key == keycode && held_keys.contains(modifier)
+						{
+							state.push(1.);
+							chorded = true;
+						}
+					}
+				}
+				if !chorded
+				{
+					if let Some(state) = self.input_state.get_mut(&Input::Keyboard(*keycode))
+					{
+						state.push(1.);
+					}
+				}
+			}
+			allegro::Event::KeyUp { .. } | allegro::Event::MouseButtonUp { .. } =>
+			{
+				self.decode_release(event);
+			}
+			allegro::Event::MouseButtonDown { button, .. } =>
 			{
 				if let Some(state) = self
 					.input_state
 					.get_mut(&Input::MouseButton(*button as i32))
 				{
-					state.push(0.);
+					state.push(1.);
 				}
 			}
 			allegro::Event::MouseAxes { dx, dy, dz, .. } =>
@@ -669,6 +1024,10 @@ impl ControlsHandler
 
 	pub fn get_action_state(&mut self, action: Action) -> f32
 	{
+		if self.consumed.contains(&action)
+		{
+			return 0.;
+		}
 		let mut ret = 0.;
 		if let Some(inputs) = self.controls.action_to_inputs.get(&action)
 		{
@@ -683,6 +1042,79 @@ impl ControlsHandler
 		return ret;
 	}
 
+	/// Whether the action's state exceeds its press threshold.
+	pub fn is_pressed(&mut self, action: Action) -> bool
+	{
+		self.get_action_state(action) > self.get_press_threshold(action)
+	}
+
+	/// Marks the action as handled, so it reads as 0 until `clear_consumed` is called (once per
+	/// logic tick by the game loop).
+	pub fn consume(&mut self, action: Action)
+	{
+		self.clear_action_state(action);
+		self.consumed.insert(action);
+	}
+
+	pub fn clear_consumed(&mut self)
+	{
+		self.consumed.clear();
+	}
+
+	fn tick_pressed(&mut self, action: Action) -> bool
+	{
+		if let Some(&pressed) = self.tick_pressed.get(&action)
+		{
+			return pressed;
+		}
+		let pressed = self.is_pressed(action);
+		self.tick_pressed.insert(action, pressed);
+		pressed
+	}
+
+	/// Whether the action became pressed this tick.
+	pub fn action_pressed(&mut self, action: Action) -> bool
+	{
+		self.tick_pressed(action) && !self.prev_pressed.contains(&action)
+	}
+
+	/// Whether the action stopped being pressed this tick.
+	pub fn action_released(&mut self, action: Action) -> bool
+	{
+		!self.tick_pressed(action) && self.prev_pressed.contains(&action)
+	}
+
+	/// Remembers the action states for `action_pressed` and `action_released`, and clears the
+	/// consumed actions. Called once per logic tick by the game loop.
+	pub fn end_tick(&mut self)
+	{
+		let mut prev_pressed = BTreeSet::new();
+		for (action, inputs) in &self.controls.action_to_inputs
+		{
+			let pressed = match self.tick_pressed.get(action)
+			{
+				Some(&pressed) => pressed,
+				None =>
+				{
+					let state: f32 = inputs
+						.iter()
+						.flatten()
+						.map(|input| self.input_state[input].peek())
+						.sum();
+					!self.consumed.contains(action)
+						&& state > self.controls.get_press_threshold(*action)
+				}
+			};
+			if pressed
+			{
+				prev_pressed.insert(*action);
+			}
+		}
+		self.prev_pressed = prev_pressed;
+		self.tick_pressed.clear();
+		self.clear_consumed();
+	}
+
 	pub fn clear_action_state(&mut self, action: Action)
 	{
 		if let Some(inputs) = self.controls.action_to_inputs.get(&action)
@@ -707,39 +1139,9 @@ impl ControlsHandler
 		&mut self, action: Action, index: usize, event: &allegro::Event,
 	) -> Option<bool>
 	{
-		let mut handled = false;
-		let new_input = match event
-		{
-			allegro::Event::KeyDown { keycode, .. } =>
-			{
-				handled = true;
-				match *keycode
-				{
-					allegro::KeyCode::Escape => None,
-					keycode => Some(Input::Keyboard(keycode)),
-				}
-			}
-			allegro::Event::MouseButtonUp { button, .. } =>
-			{
-				handled = true;
-				Some(Input::MouseButton(*button as i32))
-			}
-			allegro::Event::MouseAxes { dx, dy, dz, .. } =>
-			{
-				handled = true;
-				match (dx.cmp(&0), dy.cmp(&0), dz.cmp(&0))
-				{
-					(Ordering::Less, _, _) => Some(Input::MouseXNeg),
-					(Ordering::Greater, _, _) => Some(Input::MouseXPos),
-					(_, Ordering::Less, _) => Some(Input::MouseYNeg),
-					(_, Ordering::Greater, _) => Some(Input::MouseYPos),
-					(_, _, Ordering::Less) => Some(Input::MouseZNeg),
-					(_, _, Ordering::Greater) => Some(Input::MouseZPos),
-					_ => None,
-				}
-			}
-			_ => None,
-		};
+		let decoded = event_to_input(event);
+		let handled = decoded.is_some();
+		let new_input = decoded.flatten();
 		if let Some(new_input) = new_input
 		{
 			if self.input_to_action.contains_key(&new_input)
@@ -774,3 +1176,207 @@ impl ControlsHandler
 		}
 	}
 }
+
+#[test]
+fn test_press_threshold()
+{
+	let mut controls = ControlsHandler::new(Controls::new());
+	let input = Input::Keyboard(allegro::KeyCode::Space);
+
+	controls.input_state.get_mut(&input).unwrap().push(0.4);
+	assert!(!controls.is_pressed(Action::Move));
+
+	controls.set_press_threshold(Action::Move, 0.3);
+	controls.input_state.get_mut(&input).unwrap().push(0.4);
+	assert!(controls.is_pressed(Action::Move));
+}
+
+#[test]
+fn test_consume()
+{
+	let mut controls = ControlsHandler::new(Controls::new());
+	let input = Input::Keyboard(allegro::KeyCode::Space);
+
+	controls.input_state.get_mut(&input).unwrap().push(1.);
+	controls.consume(Action::Move);
+	assert_eq!(controls.get_action_state(Action::Move), 0.);
+
+	controls.input_state.get_mut(&input).unwrap().push(1.);
+	assert_eq!(controls.get_action_state(Action::Move), 0.);
+
+	controls.clear_consumed();
+	controls.input_state.get_mut(&input).unwrap().push(1.);
+	assert_eq!(controls.get_action_state(Action::Move), 1.);
+}
+
+#[test]
+fn test_capture_and_set_binding()
+{
+	let mut controls = ControlsHandler::new(Controls::new());
+	let key_down = |keycode| allegro::Event::KeyDown {
+		source: std::ptr::null_mut(),
+		timestamp: 0.,
+		keycode: keycode,
+		display: std::ptr::null_mut(),
+	};
+
+	controls.capture_next_input();
+	assert!(controls.is_capturing());
+	controls.decode_event(&key_down(allegro::KeyCode::E));
+	assert!(!controls.is_capturing());
+	let input = controls.take_captured_input();
+	assert_eq!(input, Some(Input::Keyboard(allegro::KeyCode::E)));
+
+	assert_eq!(controls.set_binding(Action::Move, 1, input).unwrap(), None);
+	// Rebinding the same input to the other slot clears the first one.
+	assert_eq!(
+		controls.set_binding(Action::Move, 0, input).unwrap(),
+		Some((Action::Move, 1))
+	);
+	assert_eq!(controls.get_inputs(Action::Move), Some(&[input, None]));
+	assert!(controls.set_binding(Action::Move, 2, input).is_err());
+
+	controls.capture_next_input();
+	controls.decode_event(&key_down(allegro::KeyCode::Escape));
+	assert!(!controls.is_capturing());
+	assert_eq!(controls.take_captured_input(), None);
+
+	// A bound key released while capturing doesn't stay pressed.
+	controls.decode_event(&key_down(allegro::KeyCode::E));
+	assert_eq!(controls.get_action_state(Action::Move), 1.);
+	controls.capture_next_input();
+	controls.decode_event(&allegro::Event::KeyUp {
+		source: std::ptr::null_mut(),
+		timestamp: 0.,
+		keycode: allegro::KeyCode::E,
+		display: std::ptr::null_mut(),
+	});
+	controls.decode_event(&key_down(allegro::KeyCode::Escape));
+	assert_eq!(controls.get_action_state(Action::Move), 0.);
+}
+
+#[test]
+fn test_input_display()
+{
+	assert_eq!(
+		Input::Keyboard(allegro::KeyCode::Up).to_string(),
+		"Arrow Up"
+	);
+	assert_eq!(Input::Keyboard(allegro::KeyCode::A).to_string(), "A");
+	assert_eq!(Input::Keyboard(allegro::KeyCode::Pad5).to_string(), "Pad 5");
+	assert_eq!(Input::MouseButton(1).to_string(), "Mouse Left");
+	assert_eq!(Input::MouseButton(4).to_string(), "Mouse 4");
+	assert_eq!(Input::MouseZPos.to_string(), "Mouse Wheel Up");
+}
+
+#[test]
+fn test_chord()
+{
+	use allegro::KeyCode;
+	let mut controls = ControlsHandler::new(Controls::new());
+	let key_event = |keycode, down| {
+		if down
+		{
+			allegro::Event::KeyDown {
+				source: std::ptr::null_mut(),
+				timestamp: 0.,
+				keycode: keycode,
+				display: std::ptr::null_mut(),
+			}
+		}
+		else
+		{
+			allegro::Event::KeyUp {
+				source: std::ptr::null_mut(),
+				timestamp: 0.,
+				keycode: keycode,
+				display: std::ptr::null_mut(),
+			}
+		}
+	};
+	let chord = Input::Chord(KeyCode::LCtrl, KeyCode::S);
+	assert_eq!(Input::from_str(&chord.to_str()), Some(chord));
+	assert_eq!(chord.to_string(), "Left Ctrl+S");
+	assert_eq!(Input::from_str("MouseX+"), Some(Input::MouseXPos));
+
+	controls
+		.set_binding(Action::Move, 0, Some(Input::Keyboard(KeyCode::S)))
+		.unwrap();
+	controls.set_binding(Action::Move, 1, Some(chord)).unwrap();
+	let get =
+		|controls: &mut ControlsHandler, input| controls.input_state.get_mut(&input).unwrap().get();
+
+	// The key alone.
+	controls.decode_event(&key_event(KeyCode::S, true));
+	assert_eq!(get(&mut controls, Input::Keyboard(KeyCode::S)), 1.);
+	assert_eq!(get(&mut controls, chord), 0.);
+	controls.decode_event(&key_event(KeyCode::S, false));
+
+	// The chord doesn't fire the key.
+	controls.decode_event(&key_event(KeyCode::LCtrl, true));
+	controls.decode_event(&key_event(KeyCode::S, true));
+	assert_eq!(get(&mut controls, Input::Keyboard(KeyCode::S)), 0.);
+	assert_eq!(get(&mut controls, chord), 1.);
+	controls.decode_event(&key_event(KeyCode::LCtrl, false));
+	assert_eq!(get(&mut controls, chord), 0.);
+
+	// Capturing a chord.
+	controls.capture_next_input();
+	controls.decode_event(&key_event(KeyCode::RShift, true));
+	assert!(controls.is_capturing());
+	controls.decode_event(&key_event(KeyCode::D, true));
+	assert_eq!(
+		controls.take_captured_input(),
+		Some(Input::Chord(KeyCode::RShift, KeyCode::D))
+	);
+}
+
+#[test]
+fn test_action_edges()
+{
+	let mut controls = ControlsHandler::new(Controls::new());
+	let key_event = |down| {
+		if down
+		{
+			allegro::Event::KeyDown {
+				source: std::ptr::null_mut(),
+				timestamp: 0.,
+				keycode: allegro::KeyCode::Space,
+				display: std::ptr::null_mut(),
+			}
+		}
+		else
+		{
+			allegro::Event::KeyUp {
+				source: std::ptr::null_mut(),
+				timestamp: 0.,
+				keycode: allegro::KeyCode::Space,
+				display: std::ptr::null_mut(),
+			}
+		}
+	};
+
+	// Pressed on the first tick, held on the second.
+	controls.decode_event(&key_event(true));
+	assert!(controls.action_pressed(Action::Move));
+	assert!(controls.action_pressed(Action::Move));
+	assert!(!controls.action_released(Action::Move));
+	controls.end_tick();
+	assert!(!controls.action_pressed(Action::Move));
+	assert!(controls.is_pressed(Action::Move));
+	controls.end_tick();
+
+	// Released.
+	controls.decode_event(&key_event(false));
+	assert!(controls.action_released(Action::Move));
+	controls.end_tick();
+	assert!(!controls.action_released(Action::Move));
+	controls.end_tick();
+
+	// A tap within one tick is a press, then a release on the next tick.
+	controls.decode_event(&key_event(true));
+	controls.decode_event(&key_event(false));
+	assert!(controls.action_pressed(Action::Move));
+	controls.end_tick();
+	assert!(controls.action_released(Action::Move));
+}
@@ -1,6 +1,6 @@
 use crate::error::Result;
 use crate::utils::DT;
-use crate::{astar, components as comps, controls, game_state, sprite, ui, utils};
+use crate::{astar, components as comps, controls, game_state, screen, sprite, ui, utils};
 use allegro::*;
 use allegro_font::*;
 use na::{
@@ -27,14 +27,17 @@ impl Game
 			subscreens: ui::SubScreens::new(state),
 		})
 	}
+}
 
-	pub fn logic(
-		&mut self, state: &mut game_state::GameState,
-	) -> Result<Option<game_state::NextScreen>>
+impl screen::Screen<game_state::GameState, game_state::NextScreen> for Game
+{
+	fn logic(&mut self, state: &mut game_state::GameState)
+		-> Result<Option<game_state::NextScreen>>
 	{
 		if self.subscreens.is_empty()
 		{
-			self.map.logic(state)
+			let ctx = state.frame_ctx();
+			self.map.logic(&ctx, state)
 		}
 		else
 		{
@@ -42,7 +45,7 @@ impl Game
 		}
 	}
 
-	pub fn input(
+	fn input(
 		&mut self, event: &Event, state: &mut game_state::GameState,
 	) -> Result<Option<game_state::NextScreen>>
 	{
@@ -110,7 +113,7 @@ impl Game
 		Ok(None)
 	}
 
-	pub fn draw(&mut self, state: &game_state::GameState) -> Result<()>
+	fn draw(&mut self, state: &game_state::GameState) -> Result<()>
 	{
 		if !self.subscreens.is_empty()
 		{
@@ -124,7 +127,7 @@ impl Game
 		Ok(())
 	}
 
-	pub fn resize(&mut self, state: &game_state::GameState)
+	fn resize(&mut self, state: &game_state::GameState)
 	{
 		self.subscreens.resize(state);
 	}
@@ -151,8 +154,9 @@ impl Map
 		Ok(Self { world: world })
 	}
 
-	fn logic(&mut self, state: &mut game_state::GameState)
-		-> Result<Option<game_state::NextScreen>>
+	fn logic(
+		&mut self, ctx: &game_state::FrameCtx, state: &mut game_state::GameState,
+	) -> Result<Option<game_state::NextScreen>>
 	{
 		let mut to_die = vec![];
 
@@ -163,18 +167,18 @@ impl Map
 		}
 
 		// Input.
-		if state.controls.get_action_state(controls::Action::Move) > 0.5
+		if state.controls.is_pressed(controls::Action::Move)
 		{
 			for (_, position) in self.world.query::<&mut comps::Position>().iter()
 			{
-				position.pos.y += 100. * DT;
+				position.pos.y += 100. * ctx.dt;
 			}
 		}
 
 		// Movement.
 		for (_, position) in self.world.query::<&mut comps::Position>().iter()
 		{
-			position.pos.x += 1500. * DT;
+			position.pos.x += 1500. * ctx.dt;
 			if position.pos.x > state.buffer_width()
 			{
 				position.pos.x %= state.buffer_width();
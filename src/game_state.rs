@@ -1,17 +1,21 @@
 use crate::error::Result;
-use crate::{atlas, controls, sfx, sprite, utils};
+use crate::{atlas, controls, loader, recorder, sfx, sprite, utils};
 use allegro::*;
 use allegro_font::*;
 use allegro_image::*;
 use allegro_primitives::*;
 use allegro_ttf::*;
 use nalgebra::Point2;
+use rand::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::sync::Weak;
 use std::{fmt, path};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Default for `GameState::fixed_buffer_size`.
+pub const DEFAULT_BUFFER_SIZE: (i32, i32) = (640, 480);
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Options
@@ -27,10 +31,27 @@ pub struct Options
 	pub grab_mouse: bool,
 	pub ui_scale: f32,
 	pub frac_scale: bool,
+	/// If set, all engine randomness is derived from this seed.
+	#[serde(default)]
+	pub seed: Option<u64>,
+	/// Frame rate cap while the window is unfocused. 0 stops drawing entirely.
+	#[serde(default)]
+	pub unfocused_fps: Option<u32>,
+	/// Position of the window when it was last windowed, restored on startup.
+	#[serde(default)]
+	pub window_position: Option<(i32, i32)>,
+	/// Color of the borders around the scaled buffers.
+	#[serde(default = "default_letterbox_color", with = "utils::hex_color")]
+	pub letterbox_color: Color,
 
 	pub controls: controls::Controls,
 }
 
+fn default_letterbox_color() -> Color
+{
+	Color::from_rgb_f(0., 0., 0.)
+}
+
 impl Default for Options
 {
 	fn default() -> Self
@@ -47,11 +68,39 @@ impl Default for Options
 			grab_mouse: false,
 			ui_scale: 1.,
 			frac_scale: true,
+			seed: None,
+			unfocused_fps: None,
+			window_position: None,
+			letterbox_color: default_letterbox_color(),
 			controls: controls::Controls::new(),
 		}
 	}
 }
 
+/// A snapshot of the timing state, taken once per logic tick so that all systems see a
+/// consistent view of the frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrameCtx
+{
+	pub dt: f32,
+	pub time: f64,
+	pub alpha: f32,
+	pub tick: i64,
+}
+
+impl FrameCtx
+{
+	pub fn new(tick: i64, alpha: f32) -> Self
+	{
+		Self {
+			dt: utils::DT,
+			time: tick as f64 * utils::DT as f64,
+			alpha: alpha,
+			tick: tick,
+		}
+	}
+}
+
 #[derive(Debug)]
 pub enum NextScreen
 {
@@ -72,6 +121,7 @@ pub struct GameState
 	pub paused: bool,
 
 	pub sfx: sfx::Sfx,
+	pub rng: StdRng,
 	pub atlas: atlas::Atlas,
 	pub ui_font: Option<Font>,
 	pub options: Options,
@@ -86,10 +136,35 @@ pub struct GameState
 	pub display_height: f32,
 	pub buffer1: Option<Bitmap>,
 	pub buffer2: Option<Bitmap>,
+	/// Internal resolution to draw at before scaling to the display. `None` draws at the
+	/// display's resolution. Takes effect on the next `resize_display`.
+	pub fixed_buffer_size: Option<(i32, i32)>,
+	/// If set, the buffers are scaled up by a whole multiple no larger than this, regardless of
+	/// `Options::frac_scale`.
+	pub max_integer_scale: Option<i32>,
+	post_process: Option<PostProcess>,
+	pub recorder: recorder::FrameRecorder,
+	pub show_fps: bool,
+	pub frame_stats: utils::FrameStats,
 
 	pub alpha: f32,
 }
 
+/// `name` inside the user data directory, or the working directory without `use_user_settings`.
+fn user_data_path(core: &Core, name: &str) -> Result<path::PathBuf>
+{
+	let mut path_buf = path::PathBuf::new();
+	if cfg!(feature = "use_user_settings")
+	{
+		path_buf.push(
+			core.get_standard_path(StandardPath::UserData)
+				.map_err(|_| "Couldn't get standard path".to_string())?,
+		);
+	}
+	path_buf.push(name);
+	Ok(path_buf)
+}
+
 pub fn load_options(core: &Core) -> Result<Options>
 {
 	let mut path_buf = path::PathBuf::new();
@@ -123,7 +198,92 @@ pub fn save_options(core: &Core, options: &Options) -> Result<()>
 	}
 	std::fs::create_dir_all(&path_buf).map_err(|_| "Couldn't create directory".to_string())?;
 	path_buf.push("options.cfg");
-	utils::save_config(path_buf.to_str().unwrap(), &options)
+	utils::save_config_with_backup(path_buf.to_str().unwrap(), &options)
+}
+
+/// A shader used instead of `data/scale` when scaling the buffers to the display.
+pub struct PostProcess
+{
+	pub shader: Weak<Shader>,
+	/// Extra uniforms, set after the `bitmap_width`, `bitmap_height` and `scale` uniforms.
+	pub uniforms: Vec<(String, Vec<f32>)>,
+}
+
+/// Moves a window at `pos` of `size` onto the monitor it overlaps the most, or the first monitor
+/// if it's entirely off-screen.
+pub fn clamp_window_position(
+	pos: (i32, i32), size: (i32, i32), monitors: &[MonitorInfo],
+) -> (i32, i32)
+{
+	let overlap = |m: &MonitorInfo| {
+		let w = utils::min(pos.0 + size.0, m.x2) - utils::max(pos.0, m.x1);
+		let h = utils::min(pos.1 + size.1, m.y2) - utils::max(pos.1, m.y1);
+		utils::max(w, 0) as i64 * utils::max(h, 0) as i64
+	};
+	let mut best: Option<&MonitorInfo> = None;
+	for m in monitors
+	{
+		if best.map(|b| overlap(m) > overlap(b)).unwrap_or(true)
+		{
+			best = Some(m);
+		}
+	}
+	match best
+	{
+		Some(m) => (
+			utils::clamp(pos.0, m.x1, utils::max(m.x1, m.x2 - size.0)),
+			utils::clamp(pos.1, m.y1, utils::max(m.y1, m.y2 - size.1)),
+		),
+		None => pos,
+	}
+}
+
+/// Size of the internal buffers for a display of the given size.
+fn buffer_size(
+	fixed_buffer_size: Option<(i32, i32)>, display_width: i32, display_height: i32,
+) -> (i32, i32)
+{
+	fixed_buffer_size.unwrap_or((display_width, display_height))
+}
+
+/// The size to (re)create the buffers at, or `None` if the `current` ones can be kept.
+fn buffers_to_create(
+	current: Option<(i32, i32)>, fixed_buffer_size: Option<(i32, i32)>, display_width: i32,
+	display_height: i32,
+) -> Option<(i32, i32)>
+{
+	let size = buffer_size(fixed_buffer_size, display_width, display_height);
+	if current == Some(size)
+	{
+		None
+	}
+	else
+	{
+		Some(size)
+	}
+}
+
+/// Scale at which the buffers fit in the display. Buffers larger than the display are always
+/// scaled down to fit, since there's no integer scale for that.
+fn draw_scale(
+	display_size: (i32, i32), buffer_size: (i32, i32), frac_scale: bool,
+	max_integer_scale: Option<i32>,
+) -> f32
+{
+	let scale = utils::min(
+		display_size.0 as f32 / buffer_size.0 as f32,
+		display_size.1 as f32 / buffer_size.1 as f32,
+	);
+	if scale < 1.
+	{
+		return scale;
+	}
+	match max_integer_scale
+	{
+		Some(max_scale) => utils::clamp(scale.floor(), 1., max_scale as f32),
+		None if !frac_scale => scale.floor(),
+		None => scale,
+	}
 }
 
 impl GameState
@@ -144,11 +304,13 @@ impl GameState
 		core.install_mouse()
 			.map_err(|_| "Couldn't install mouse".to_string())?;
 
-		let sfx = sfx::Sfx::new(options.sfx_volume, options.music_volume, &core)?;
+		let mut rng = utils::make_rng(options.seed);
+		let sfx = sfx::Sfx::new(options.sfx_volume, options.music_volume, rng.gen(), &core)?;
 		//sfx.set_music_file("data/lemonade-sinus.xm");
 		//sfx.play_music()?;
 
 		let controls = controls::ControlsHandler::new(options.controls.clone());
+		let recording_dir = user_data_path(&core, "recording")?;
 		Ok(Self {
 			options: options,
 			core: core,
@@ -160,6 +322,7 @@ impl GameState
 			font: font,
 			ttf: ttf,
 			sfx: sfx,
+			rng: rng,
 			paused: false,
 			atlas: atlas::Atlas::new(1024),
 			ui_font: None,
@@ -168,6 +331,12 @@ impl GameState
 			display_height: 0.,
 			buffer1: None,
 			buffer2: None,
+			fixed_buffer_size: Some(DEFAULT_BUFFER_SIZE),
+			max_integer_scale: None,
+			post_process: None,
+			recorder: recorder::FrameRecorder::new(recording_dir.to_str().unwrap(), 600),
+			show_fps: false,
+			frame_stats: utils::FrameStats::new(60),
 			controls: controls,
 			track_mouse: true,
 			mouse_pos: Point2::new(0, 0),
@@ -195,43 +364,134 @@ impl GameState
 		self.buffer1().get_height() as f32
 	}
 
-	pub fn ui_font(&self) -> &Font
+	/// Saves the last drawn frame at the internal resolution, returning the file name.
+	pub fn save_screenshot(&self) -> Result<String>
 	{
-		self.ui_font.as_ref().unwrap()
+		let mut path_buf = user_data_path(&self.core, "screenshots")?;
+		let time = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.unwrap_or_default();
+		path_buf.push(format!(
+			"screenshot_{}_{:03}.png",
+			time.as_secs(),
+			time.subsec_millis()
+		));
+		let path = path_buf.to_str().unwrap().to_string();
+		utils::save_screenshot(self.buffer2(), &path)?;
+		Ok(path)
 	}
 
-	pub fn resize_display(&mut self, display: &Display) -> Result<()>
+	/// Draws the frame timings in the top left corner, if `show_fps` is set.
+	pub fn draw_fps_overlay(&self)
 	{
-		const FIXED_BUFFER: bool = true;
+		if !self.show_fps
+		{
+			return;
+		}
+		let lh = self.ui_font().get_line_height() as f32;
+		let lines = [
+			format!("FPS: {:.1}", self.frame_stats.fps()),
+			format!("Frame: {:.2} ms", 1000. * self.frame_stats.frame_time()),
+			format!("Logic/draw: {:.2}", self.frame_stats.logics_per_draw()),
+		];
+		for (i, line) in lines.iter().enumerate()
+		{
+			self.core.draw_text(
+				self.ui_font(),
+				Color::from_rgb_f(1., 1., 0.),
+				lh / 2.,
+				lh / 2. + i as f32 * lh,
+				FontAlign::Left,
+				line,
+			);
+		}
+	}
+
+	/// Replaces the default scaling shader with `shader`, e.g. for CRT effects. The shader gets
+	/// the same uniforms as `data/scale`, plus any set via `set_post_process_uniform`.
+	pub fn set_post_process(&mut self, shader: Weak<Shader>)
+	{
+		self.post_process = Some(PostProcess {
+			shader: shader,
+			uniforms: vec![],
+		});
+	}
 
-		let buffer_width;
-		let buffer_height;
-		if FIXED_BUFFER
+	pub fn set_post_process_uniform(&mut self, name: &str, values: &[f32])
+	{
+		if let Some(post_process) = self.post_process.as_mut()
 		{
-			buffer_width = 640;
-			buffer_height = 480;
+			let values = values.to_vec();
+			match post_process.uniforms.iter_mut().find(|(n, _)| n == name)
+			{
+				Some(uniform) => uniform.1 = values,
+				None => post_process.uniforms.push((name.to_string(), values)),
+			}
 		}
-		else
+	}
+
+	pub fn clear_post_process(&mut self)
+	{
+		self.post_process = None;
+	}
+
+	pub fn post_process(&self) -> Option<&PostProcess>
+	{
+		self.post_process.as_ref()
+	}
+
+	/// Remembers the window position and size in the options and saves them, unless
+	/// fullscreen. Failing to save isn't fatal, so it's only logged.
+	pub fn save_window_geometry(&mut self, display: &Display)
+	{
+		if self.options.fullscreen
+		{
+			return;
+		}
+		self.options.window_position = Some(display.get_window_position());
+		self.options.width = display.get_width();
+		self.options.height = display.get_height();
+		if let Err(e) = save_options(&self.core, &self.options)
 		{
-			buffer_width = display.get_width();
-			buffer_height = display.get_height();
+			println!("Couldn't save the window geometry: {}", e);
 		}
+	}
+
+	pub fn ui_font(&self) -> &Font
+	{
+		self.ui_font.as_ref().unwrap()
+	}
+
+	pub fn resize_display(&mut self, display: &Display) -> Result<()>
+	{
+		let (buffer_width, buffer_height) = buffer_size(
+			self.fixed_buffer_size,
+			display.get_width(),
+			display.get_height(),
+		);
 
 		self.display_width = display.get_width() as f32;
 		self.display_height = display.get_height() as f32;
-		self.draw_scale = utils::min(
-			(display.get_width() as f32) / (buffer_width as f32),
-			(display.get_height() as f32) / (buffer_height as f32),
+		self.draw_scale = draw_scale(
+			(display.get_width(), display.get_height()),
+			(buffer_width, buffer_height),
+			self.options.frac_scale,
+			self.max_integer_scale,
 		);
-		if !self.options.frac_scale
-		{
-			self.draw_scale = self.draw_scale.floor();
-		}
 
-		if self.buffer1.is_none() || !FIXED_BUFFER
+		let current = self
+			.buffer1
+			.as_ref()
+			.map(|b| (b.get_width(), b.get_height()));
+		if let Some((width, height)) = buffers_to_create(
+			current,
+			self.fixed_buffer_size,
+			display.get_width(),
+			display.get_height(),
+		)
 		{
-			self.buffer1 = Some(Bitmap::new(&self.core, buffer_width, buffer_height).unwrap());
-			self.buffer2 = Some(Bitmap::new(&self.core, buffer_width, buffer_height).unwrap());
+			self.buffer1 = Some(Bitmap::new(&self.core, width, height).unwrap());
+			self.buffer2 = Some(Bitmap::new(&self.core, width, height).unwrap());
 		}
 
 		self.ui_font = Some(utils::load_ttf_font(
@@ -258,6 +518,50 @@ impl GameState
 		})
 	}
 
+	/// Starts decoding bitmaps on a worker thread, skipping ones that are already cached. Call
+	/// `receive_prefetched` every frame to move them into the cache.
+	pub fn prefetch(&self, names: &[&str]) -> loader::LoadHandle
+	{
+		loader::LoadHandle::spawn(
+			names
+				.iter()
+				.filter(|name| !self.bitmaps.contains_key(**name))
+				.map(|name| name.to_string())
+				.collect(),
+		)
+	}
+
+	/// Caches the bitmaps `handle` has decoded so far. This creates the textures, so it must
+	/// be called from the main thread. Bitmaps that failed to load are reported after the
+	/// rest are cached.
+	pub fn receive_prefetched(&mut self, handle: &mut loader::LoadHandle) -> Result<()>
+	{
+		let mut errors = vec![];
+		for (name, bitmap) in handle.take_decoded()
+		{
+			let bitmap = bitmap.and_then(|b| {
+				b.maybe_clone()
+					.map_err(|_| format!("Couldn't upload {}", name))
+			});
+			match bitmap
+			{
+				Ok(bitmap) =>
+				{
+					self.bitmaps.insert(name, bitmap);
+				}
+				Err(e) => errors.push(e),
+			}
+		}
+		if errors.is_empty()
+		{
+			Ok(())
+		}
+		else
+		{
+			Err(errors.join("\n").into())
+		}
+	}
+
 	pub fn cache_sprite<'l>(&'l mut self, name: &str) -> Result<&'l sprite::Sprite>
 	{
 		Ok(match self.sprites.entry(name.to_string())
@@ -287,4 +591,122 @@ impl GameState
 	{
 		self.tick as f64 * utils::DT as f64
 	}
+
+	pub fn frame_ctx(&self) -> FrameCtx
+	{
+		FrameCtx::new(self.tick, self.alpha)
+	}
+}
+
+#[test]
+fn test_frame_ctx()
+{
+	// Time advances by exactly one DT per tick, without drifting.
+	let mut prev = FrameCtx::new(0, 0.);
+	assert_eq!(prev.time, 0.);
+	for tick in 1..1000
+	{
+		let ctx = FrameCtx::new(tick, 0.5);
+		assert!((ctx.time - prev.time - utils::DT as f64).abs() < 1e-9);
+		prev = ctx;
+	}
+	// An hour in.
+	let ctx = FrameCtx::new(60 * 60 * 60, 0.);
+	assert!((ctx.time - 3600.).abs() < 1e-3);
+}
+
+#[test]
+fn test_buffer_size()
+{
+	// The default fixed buffer, on a display smaller than it.
+	let buffer = buffer_size(Some(DEFAULT_BUFFER_SIZE), 320, 200);
+	assert_eq!(buffer, (640, 480));
+	for frac_scale in [false, true]
+	{
+		for max_integer_scale in [None, Some(3)]
+		{
+			let scale = draw_scale((320, 200), buffer, frac_scale, max_integer_scale);
+			assert!(scale > 0.);
+			assert!(buffer.0 as f32 * scale <= 320. && buffer.1 as f32 * scale <= 200.);
+		}
+	}
+
+	// Native size draws 1:1 regardless of the scaling options.
+	let buffer = buffer_size(None, 1366, 768);
+	assert_eq!(buffer, (1366, 768));
+	assert_eq!(draw_scale((1366, 768), buffer, false, None), 1.);
+	assert_eq!(draw_scale((1366, 768), buffer, true, Some(3)), 1.);
+}
+
+#[test]
+fn test_buffers_to_create()
+{
+	// First resize always creates the buffers.
+	assert_eq!(buffers_to_create(None, None, 800, 600), Some((800, 600)));
+	assert_eq!(
+		buffers_to_create(None, Some((320, 240)), 800, 600),
+		Some((320, 240))
+	);
+
+	// A fixed size ignores display resizes, and changing it recreates the buffers.
+	assert_eq!(
+		buffers_to_create(Some((320, 240)), Some((320, 240)), 1024, 768),
+		None
+	);
+	assert_eq!(
+		buffers_to_create(Some((320, 240)), Some((640, 360)), 1024, 768),
+		Some((640, 360))
+	);
+
+	// Native size follows the display.
+	assert_eq!(buffers_to_create(Some((800, 600)), None, 800, 600), None);
+	assert_eq!(
+		buffers_to_create(Some((800, 600)), None, 1024, 768),
+		Some((1024, 768))
+	);
+}
+
+#[test]
+fn test_draw_scale()
+{
+	assert_eq!(draw_scale((1000, 1000), (400, 300), true, None), 2.5);
+	assert_eq!(draw_scale((1000, 1000), (400, 300), false, None), 2.);
+	assert_eq!(draw_scale((1920, 1080), (320, 180), true, Some(3)), 3.);
+	assert_eq!(draw_scale((1920, 1080), (320, 180), true, Some(8)), 6.);
+	assert_eq!(draw_scale((200, 200), (320, 180), true, Some(3)), 0.625);
+}
+
+#[test]
+fn test_clamp_window_position()
+{
+	let monitors = [
+		MonitorInfo {
+			x1: 0,
+			y1: 0,
+			x2: 1920,
+			y2: 1080,
+		},
+		MonitorInfo {
+			x1: 1920,
+			y1: 0,
+			x2: 3200,
+			y2: 1024,
+		},
+	];
+	// Fully on a monitor.
+	assert_eq!(
+		clamp_window_position((100, 100), (800, 600), &monitors),
+		(100, 100)
+	);
+	// Straddling, mostly on the second monitor.
+	assert_eq!(
+		clamp_window_position((1800, 900), (800, 600), &monitors),
+		(1920, 424)
+	);
+	// Entirely off-screen.
+	assert_eq!(
+		clamp_window_position((-5000, 100), (800, 600), &monitors),
+		(0, 100)
+	);
+	assert_eq!(clamp_window_position((10, 10), (800, 600), &[]), (10, 10));
 }
@@ -0,0 +1,169 @@
+use allegro::*;
+use std::ffi::CString;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// A decoded memory bitmap, or an error message.
+type Decoded = (String, std::result::Result<Bitmap, String>);
+
+/// Bitmaps being decoded on a worker thread, see `GameState::prefetch`.
+///
+/// Only reading and decoding the image files happens on the worker, into memory bitmaps.
+/// Turning those into video bitmaps (i.e. creating GL textures) must happen on the thread
+/// that owns the display, which is what `GameState::receive_prefetched` does.
+///
+/// Dropping the handle stops the worker after the bitmap it's currently decoding, and waits for
+/// it, so the worker never outlives Allegro.
+pub struct LoadHandle
+{
+	total: usize,
+	receiver: mpsc::Receiver<Decoded>,
+	decoded: Vec<Decoded>,
+	num_received: usize,
+	cancel: Arc<AtomicBool>,
+	worker: Option<thread::JoinHandle<()>>,
+}
+
+impl LoadHandle
+{
+	/// Starts decoding `names` on a new thread.
+	pub fn spawn(names: Vec<String>) -> Self
+	{
+		Self::spawn_with(names, load_memory_bitmap)
+	}
+
+	fn spawn_with(names: Vec<String>, load: fn(&str) -> std::result::Result<Bitmap, String>)
+		-> Self
+	{
+		let (sender, receiver) = mpsc::channel();
+		let mut handle = Self::new(names.len(), receiver);
+		let cancel = handle.cancel.clone();
+		handle.worker = Some(thread::spawn(move || {
+			for name in names
+			{
+				if cancel.load(Ordering::Relaxed)
+				{
+					break;
+				}
+				let bitmap = load(&name);
+				if sender.send((name, bitmap)).is_err()
+				{
+					break;
+				}
+			}
+		}));
+		handle
+	}
+
+	fn new(total: usize, receiver: mpsc::Receiver<Decoded>) -> Self
+	{
+		Self {
+			total: total,
+			receiver: receiver,
+			decoded: vec![],
+			num_received: 0,
+			cancel: Arc::new(AtomicBool::new(false)),
+			worker: None,
+		}
+	}
+
+	/// Fraction of the bitmaps decoded so far, in [0, 1]. Doesn't block.
+	pub fn poll_progress(&mut self) -> f32
+	{
+		for item in self.receiver.try_iter()
+		{
+			self.decoded.push(item);
+			self.num_received += 1;
+		}
+		if self.total == 0
+		{
+			1.
+		}
+		else
+		{
+			self.num_received as f32 / self.total as f32
+		}
+	}
+
+	pub fn is_done(&mut self) -> bool
+	{
+		self.poll_progress() >= 1.
+	}
+
+	/// Takes the bitmaps decoded since the last call.
+	pub fn take_decoded(&mut self) -> Vec<Decoded>
+	{
+		self.poll_progress();
+		std::mem::take(&mut self.decoded)
+	}
+}
+
+impl Drop for LoadHandle
+{
+	fn drop(&mut self)
+	{
+		self.cancel.store(true, Ordering::Relaxed);
+		if let Some(worker) = self.worker.take()
+		{
+			let _ = worker.join();
+		}
+	}
+}
+
+fn load_memory_bitmap(name: &str) -> std::result::Result<Bitmap, String>
+{
+	// The worker has no display so it couldn't create anything else, but be explicit.
+	unsafe {
+		allegro_sys::al_set_new_bitmap_flags(allegro_sys::ALLEGRO_MEMORY_BITMAP as i32);
+	}
+	let c_name = CString::new(name.as_bytes()).map_err(|_| format!("Couldn't load {}", name))?;
+	let bitmap = unsafe { allegro_sys::al_load_bitmap(c_name.as_ptr()) };
+	if bitmap.is_null()
+	{
+		Err(format!("Couldn't load {}", name))
+	}
+	else
+	{
+		Ok(unsafe { Bitmap::wrap(bitmap, true) })
+	}
+}
+
+#[test]
+fn test_load_progress()
+{
+	let (sender, receiver) = mpsc::channel();
+	let mut handle = LoadHandle::new(4, receiver);
+	assert_eq!(handle.poll_progress(), 0.);
+	sender.send(("a".into(), Err("a".into()))).unwrap();
+	assert_eq!(handle.poll_progress(), 0.25);
+	sender.send(("b".into(), Err("b".into()))).unwrap();
+	assert_eq!(handle.take_decoded().len(), 2);
+	assert_eq!(handle.poll_progress(), 0.5);
+	assert!(!handle.is_done());
+
+	let (_, receiver) = mpsc::channel();
+	assert!(LoadHandle::new(0, receiver).is_done());
+}
+
+#[test]
+fn test_load_worker()
+{
+	let names: Vec<String> = (0..3).map(|i| i.to_string()).collect();
+	let mut handle = LoadHandle::spawn_with(names, |name| Err(name.to_string()));
+	while !handle.is_done()
+	{
+		thread::yield_now();
+	}
+	let decoded = handle.take_decoded();
+	assert_eq!(decoded.len(), 3);
+	assert_eq!(decoded[2].1.as_ref().err(), Some(&"2".to_string()));
+
+	// Dropping the handle mid-load stops the worker and waits for it.
+	let names: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+	let handle = LoadHandle::spawn_with(names, |name| {
+		thread::sleep(std::time::Duration::from_millis(1));
+		Err(name.to_string())
+	});
+	drop(handle);
+}
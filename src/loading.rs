@@ -0,0 +1,84 @@
+use crate::error::Result;
+use crate::{game_state, loader, screen, ui};
+
+use allegro::*;
+use allegro_font::*;
+
+/// Shows a progress bar while bitmaps are prefetched, then switches to `next`.
+pub struct Loading
+{
+	handle: loader::LoadHandle,
+	progress: f32,
+	next: Option<game_state::NextScreen>,
+}
+
+impl Loading
+{
+	pub fn new(state: &game_state::GameState, names: &[&str], next: game_state::NextScreen)
+		-> Self
+	{
+		Self {
+			handle: state.prefetch(names),
+			progress: 0.,
+			next: Some(next),
+		}
+	}
+}
+
+impl screen::Screen<game_state::GameState, game_state::NextScreen> for Loading
+{
+	fn input(
+		&mut self, _event: &Event, _state: &mut game_state::GameState,
+	) -> Result<Option<game_state::NextScreen>>
+	{
+		Ok(None)
+	}
+
+	fn logic(&mut self, state: &mut game_state::GameState)
+		-> Result<Option<game_state::NextScreen>>
+	{
+		// Poll first, so everything counted by the progress gets received below.
+		self.progress = self.handle.poll_progress();
+		state.receive_prefetched(&mut self.handle)?;
+		if self.progress >= 1.
+		{
+			Ok(self.next.take())
+		}
+		else
+		{
+			Ok(None)
+		}
+	}
+
+	fn draw(&mut self, state: &game_state::GameState) -> Result<()>
+	{
+		state.core.clear_to_color(Color::from_rgb_f(0., 0., 0.));
+		let s = state.options.ui_scale;
+		let cx = state.buffer_width() / 2.;
+		let cy = state.buffer_height() / 2.;
+		let w = s * ui::BUTTON_WIDTH;
+		let h = s * ui::BUTTON_HEIGHT / 2.;
+
+		state.core.draw_text(
+			state.ui_font(),
+			ui::LABEL,
+			cx.round(),
+			(cy - h - state.ui_font().get_line_height() as f32).round(),
+			FontAlign::Centre,
+			"Loading",
+		);
+		state.prim.draw_filled_rectangle(
+			cx - w / 2.,
+			cy,
+			cx - w / 2. + w * self.progress,
+			cy + h,
+			ui::UNSELECTED,
+		);
+		state
+			.prim
+			.draw_rectangle(cx - w / 2., cy, cx + w / 2., cy + h, ui::UNSELECTED, s);
+		Ok(())
+	}
+
+	fn resize(&mut self, _state: &game_state::GameState) {}
+}
@@ -10,7 +10,11 @@ mod controls;
 mod error;
 mod game;
 mod game_state;
+mod loader;
+mod loading;
 mod menu;
+mod recorder;
+mod screen;
 mod sfx;
 mod sprite;
 mod ui;
@@ -24,11 +28,8 @@ use rand::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 use std::rc::Rc;
 
-enum Screen
-{
-	Game(game::Game),
-	Menu(menu::Menu),
-}
+/// Bitmaps decoded behind the loading screen at startup.
+const PRELOAD_BITMAPS: &[&str] = &["data/title.png"];
 
 fn real_main() -> Result<()>
 {
@@ -50,6 +51,18 @@ fn real_main() -> Result<()>
 			DisplayOptionImportance::Suggest,
 		);
 	}
+	if let (false, Some(pos)) = (state.options.fullscreen, state.options.window_position)
+	{
+		let monitors: Vec<_> = (0..state.core.get_num_video_adapters())
+			.filter_map(|i| state.core.get_monitor_info(i).ok())
+			.collect();
+		let (x, y) = game_state::clamp_window_position(
+			pos,
+			(state.options.width, state.options.height),
+			&monitors,
+		);
+		state.core.set_new_window_position(x, y);
+	}
 	let mut display = Display::new(&state.core, state.options.width, state.options.height)
 		.map_err(|_| "Couldn't create display".to_string())?;
 
@@ -79,19 +92,27 @@ fn real_main() -> Result<()>
 
 	let mut quit = false;
 
-	let mut cur_screen = Screen::Menu(menu::Menu::new(&mut state)?);
-	//let mut cur_screen = Screen::Game(game::Game::new(
-	//	&mut state,
-	//)?);
+	let mut screens = screen::ScreenStack::new();
+	screens.push(Box::new(loading::Loading::new(
+		&state,
+		PRELOAD_BITMAPS,
+		game_state::NextScreen::Menu,
+	)));
+	screens.set_transition(screen::TransitionKind::FadeToBlack, 0.5);
+	//screens.push(Box::new(game::Game::new(&mut state)?));
 
 	let mut logics_without_draw = 0;
+	let mut focused = true;
+	let mut last_draw_time = 0.;
 	let mut old_fullscreen = state.options.fullscreen;
 	let mut old_ui_scale = state.options.ui_scale;
 	let mut old_frac_scale = state.options.frac_scale;
 
 	let mut prev_frame_start = state.core.get_time();
-	let mut logic_end = prev_frame_start;
+	// Catch up at most this many logic ticks at once, past that the game slows down.
+	let mut timestep = utils::FixedTimestep::new(utils::DT as f64, 10, prev_frame_start);
 	let mut frame_count = 0;
+	let mut last_frame_start = prev_frame_start;
 	if state.options.grab_mouse
 	{
 		state.core.grab_mouse(&display).ok();
@@ -99,10 +120,34 @@ fn real_main() -> Result<()>
 	//display.show_cursor(false).ok();
 
 	timer.start();
-	while !quit
+	// Keep pumping events while the audio fades out, so the window doesn't appear hung.
+	let mut fading_out = false;
+	while !(quit && state.sfx.fade_out_complete())
 	{
+		if quit && !fading_out
+		{
+			state
+				.sfx
+				.start_fade_out(sfx::FADEOUT_TIME, sfx::FadeCurve::Linear);
+			fading_out = true;
+		}
+
+		let draw_time = state.core.get_time();
+		screens.update(draw_time);
 		if queue.is_empty()
+			&& !utils::should_draw(
+				draw_time,
+				last_draw_time,
+				focused,
+				state.options.unfocused_fps,
+			)
 		{
+			// Throttled while unfocused, don't let that starve the logic.
+			logics_without_draw = 0;
+		}
+		else if queue.is_empty()
+		{
+			last_draw_time = draw_time;
 			if state.display_width != display.get_width() as f32
 				|| state.display_height != display.get_height() as f32
 				|| old_ui_scale != state.options.ui_scale
@@ -111,22 +156,31 @@ fn real_main() -> Result<()>
 				old_ui_scale = state.options.ui_scale;
 				old_frac_scale = state.options.frac_scale;
 				state.resize_display(&display)?;
-				match &mut cur_screen
-				{
-					Screen::Game(game) => game.resize(&state),
-					Screen::Menu(menu) => menu.resize(&state),
-				}
+				screens.resize(&state);
 			}
 
 			let frame_start = state.core.get_time();
 			state.core.set_target_bitmap(Some(state.buffer1()));
-			state.alpha = (frame_start - logic_end) as f32 / utils::DT;
+			state.alpha = timestep.alpha(frame_start);
 
-			match &mut cur_screen
+			screens.draw(&state)?;
+			let fade = screens.fade();
+			if fade > 0.
 			{
-				Screen::Game(game) => game.draw(&state)?,
-				Screen::Menu(menu) => menu.draw(&state)?,
+				state.core.use_transform(&Transform::identity());
+				state.prim.draw_filled_rectangle(
+					0.,
+					0.,
+					state.buffer_width(),
+					state.buffer_height(),
+					Color::from_rgba_f(0., 0., 0., fade),
+				);
 			}
+			state
+				.frame_stats
+				.record(frame_start - last_frame_start, logics_without_draw);
+			last_frame_start = frame_start;
+			state.draw_fps_overlay();
 
 			if state.options.vsync_method == 2
 			{
@@ -143,6 +197,11 @@ fn real_main() -> Result<()>
 			state
 				.core
 				.draw_bitmap(state.buffer1(), 0., 0., Flag::zero());
+			if let Err(e) = state.recorder.capture(state.buffer2.as_ref().unwrap())
+			{
+				println!("Couldn't record a frame, stopping: {}", e);
+				state.recorder.stop();
+			}
 
 			state.core.set_target_bitmap(Some(display.get_backbuffer()));
 
@@ -151,10 +210,11 @@ fn real_main() -> Result<()>
 			let dw = display.get_width() as f32;
 			let dh = display.get_height() as f32;
 
-			state
-				.core
-				.use_shader(Some(&*scale_shader.upgrade().unwrap()))
-				.unwrap();
+			let post_shader = state
+				.post_process()
+				.and_then(|p| p.shader.upgrade())
+				.unwrap_or_else(|| scale_shader.upgrade().unwrap());
+			state.core.use_shader(Some(&*post_shader)).unwrap();
 			state
 				.core
 				.set_shader_uniform("bitmap_width", &[bw][..])
@@ -167,8 +227,15 @@ fn real_main() -> Result<()>
 				.core
 				.set_shader_uniform("scale", &[state.draw_scale][..])
 				.ok();
+			if let Some(post_process) = state.post_process()
+			{
+				for (name, values) in &post_process.uniforms
+				{
+					state.core.set_shader_uniform(name, &values[..]).ok();
+				}
+			}
 
-			state.core.clear_to_color(Color::from_rgb_f(0., 0., 0.));
+			state.core.clear_to_color(state.options.letterbox_color);
 
 			state.core.draw_scaled_bitmap(
 				state.buffer2(),
@@ -196,15 +263,31 @@ fn real_main() -> Result<()>
 		}
 
 		let event = queue.get_next_event();
-		let mut next_screen = match &mut cur_screen
-		{
-			Screen::Game(game) => game.input(&event, &mut state)?,
-			Screen::Menu(menu) => menu.input(&event, &mut state)?,
-		};
+		let mut next_screen = screens.input(&event, &mut state)?;
 
 		match event
 		{
-			Event::DisplayClose { .. } => quit = true,
+			Event::DisplayClose { .. } =>
+			{
+				state.save_window_geometry(&display);
+				quit = true;
+			}
+			Event::KeyDown {
+				keycode: KeyCode::F12,
+				..
+			} => match state.save_screenshot()
+			{
+				Ok(path) => println!("Saved screenshot to {}", path),
+				Err(e) => println!("{}", e),
+			},
+			Event::KeyDown {
+				keycode: KeyCode::F10,
+				..
+			} => state.recorder.toggle(),
+			Event::KeyDown {
+				keycode: KeyCode::F9,
+				..
+			} => state.show_fps = !state.show_fps,
 			Event::DisplayResize { .. } =>
 			{
 				display
@@ -219,9 +302,12 @@ fn real_main() -> Result<()>
 				}
 				//display.show_cursor(false).ok();
 				state.track_mouse = true;
+				focused = true;
 			}
 			Event::DisplaySwitchOut { .. } =>
 			{
+				state.save_window_geometry(&display);
+				focused = false;
 				if state.options.grab_mouse
 				{
 					state.core.ungrab_mouse().ok();
@@ -240,61 +326,58 @@ fn real_main() -> Result<()>
 			}
 			Event::TimerTick { .. } =>
 			{
-				if logics_without_draw > 10
+				// The timer only wakes us up, the number of ticks comes from the real time.
+				for _ in 0..timestep.advance(state.core.get_time())
 				{
-					continue;
-				}
-
-				if next_screen.is_none()
-				{
-					next_screen = match &mut cur_screen
+					if next_screen.is_none()
 					{
-						Screen::Game(game) => game.logic(&mut state)?,
-						_ => None,
+						next_screen = screens.logic(&mut state)?;
 					}
-				}
 
-				if old_fullscreen != state.options.fullscreen
-				{
-					display.set_flag(FULLSCREEN_WINDOW, state.options.fullscreen);
-					old_fullscreen = state.options.fullscreen;
-				}
+					if old_fullscreen != state.options.fullscreen
+					{
+						display.set_flag(FULLSCREEN_WINDOW, state.options.fullscreen);
+						old_fullscreen = state.options.fullscreen;
+					}
 
-				logics_without_draw += 1;
-				state.sfx.update_sounds()?;
+					logics_without_draw += 1;
+					state.controls.end_tick();
+					state.sfx.update_sounds()?;
 
-				if !state.paused
-				{
-					state.tick += 1;
+					if !state.paused
+					{
+						state.tick += 1;
+					}
 				}
-				logic_end = state.core.get_time();
 			}
 			_ => (),
 		}
 
+		if quit
+		{
+			next_screen = None;
+		}
 		if let Some(next_screen) = next_screen
 		{
 			match next_screen
 			{
 				game_state::NextScreen::Game =>
 				{
-					cur_screen = Screen::Game(game::Game::new(&mut state)?);
+					screens.transition_to(Box::new(game::Game::new(&mut state)?));
 				}
 				game_state::NextScreen::Menu =>
 				{
-					cur_screen = Screen::Menu(menu::Menu::new(&mut state)?);
+					screens.transition_to(Box::new(menu::Menu::new(&mut state)?));
 				}
 				game_state::NextScreen::Quit =>
 				{
+					state.save_window_geometry(&display);
 					quit = true;
 				}
 				_ => panic!("Unknown next screen {:?}", next_screen),
 			}
 		}
 	}
-	// To avoid clicks for the final sound.
-	state.core.rest(0.25);
-
 	Ok(())
 }
 
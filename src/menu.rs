@@ -1,5 +1,5 @@
 use crate::error::Result;
-use crate::{components, controls, game_state, ui, utils};
+use crate::{components, controls, game_state, screen, ui, utils};
 
 use allegro::*;
 use allegro_font::*;
@@ -31,8 +31,11 @@ impl Menu
 
 		Ok(Self { subscreens })
 	}
+}
 
-	pub fn input(
+impl screen::Screen<game_state::GameState, game_state::NextScreen> for Menu
+{
+	fn input(
 		&mut self, event: &Event, state: &mut game_state::GameState,
 	) -> Result<Option<game_state::NextScreen>>
 	{
@@ -60,7 +63,7 @@ impl Menu
 		Ok(None)
 	}
 
-	pub fn draw(&mut self, state: &game_state::GameState) -> Result<()>
+	fn draw(&mut self, state: &game_state::GameState) -> Result<()>
 	{
 		state.core.clear_to_color(Color::from_rgb_f(0., 0., 0.5));
 		self.subscreens.draw(state);
@@ -68,7 +71,7 @@ impl Menu
 		Ok(())
 	}
 
-	pub fn resize(&mut self, state: &game_state::GameState)
+	fn resize(&mut self, state: &game_state::GameState)
 	{
 		self.subscreens.resize(state);
 	}
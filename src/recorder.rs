@@ -0,0 +1,103 @@
+use crate::error::Result;
+use crate::utils;
+use allegro::*;
+
+/// Dumps consecutive frames as numbered PNGs, for bug reports and trailers.
+///
+/// Frames are written synchronously while drawing, so expect the frame rate to drop
+/// substantially while recording.
+pub struct FrameRecorder
+{
+	dir: String,
+	max_frames: usize,
+	num_frames: usize,
+	active: bool,
+}
+
+impl FrameRecorder
+{
+	/// Frames go into `dir`, and recording stops after `max_frames` frames.
+	pub fn new(dir: &str, max_frames: usize) -> Self
+	{
+		Self {
+			dir: dir.to_string(),
+			max_frames: max_frames,
+			num_frames: 0,
+			active: false,
+		}
+	}
+
+	/// Starts recording from frame 0, overwriting any frames from a previous recording.
+	pub fn start(&mut self)
+	{
+		self.active = true;
+		self.num_frames = 0;
+	}
+
+	pub fn stop(&mut self)
+	{
+		self.active = false;
+	}
+
+	pub fn toggle(&mut self)
+	{
+		if self.active
+		{
+			self.stop();
+		}
+		else
+		{
+			self.start();
+		}
+	}
+
+	pub fn is_active(&self) -> bool
+	{
+		self.active
+	}
+
+	fn next_frame_path(&mut self) -> Option<String>
+	{
+		if !self.active
+		{
+			return None;
+		}
+		if self.num_frames >= self.max_frames
+		{
+			println!("Recorded {} frames to {}", self.num_frames, self.dir);
+			self.stop();
+			return None;
+		}
+		let path = format!("{}/frame_{:05}.png", self.dir, self.num_frames);
+		self.num_frames += 1;
+		Some(path)
+	}
+
+	/// Saves `bitmap` as the next frame, if recording.
+	pub fn capture(&mut self, bitmap: &Bitmap) -> Result<()>
+	{
+		if let Some(path) = self.next_frame_path()
+		{
+			utils::save_screenshot(bitmap, &path)?;
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn test_frame_cap()
+{
+	let mut recorder = FrameRecorder::new("rec", 2);
+	assert_eq!(recorder.next_frame_path(), None);
+	recorder.start();
+	assert_eq!(
+		recorder.next_frame_path(),
+		Some("rec/frame_00000.png".into())
+	);
+	assert_eq!(
+		recorder.next_frame_path(),
+		Some("rec/frame_00001.png".into())
+	);
+	assert_eq!(recorder.next_frame_path(), None);
+	assert!(!recorder.is_active());
+}
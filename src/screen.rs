@@ -0,0 +1,312 @@
+use crate::error::Result;
+use allegro::*;
+
+/// A top-level screen, e.g. the main menu or the game itself.
+///
+/// `S` is the game state, and `N` is what a screen returns to ask for a screen change.
+pub trait Screen<S, N>
+{
+	fn input(&mut self, event: &Event, state: &mut S) -> Result<Option<N>>;
+
+	/// Called once per logic tick.
+	fn logic(&mut self, _state: &mut S) -> Result<Option<N>>
+	{
+		Ok(None)
+	}
+
+	fn draw(&mut self, state: &S) -> Result<()>;
+
+	fn resize(&mut self, state: &S);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransitionKind
+{
+	/// Switch instantly.
+	Cut,
+	/// Fade the old screen out to black, then fade the new one in.
+	FadeToBlack,
+}
+
+struct Transition<S, N>
+{
+	start_time: f64,
+	/// The incoming screen, until it's swapped in halfway through the transition.
+	next: Option<Box<dyn Screen<S, N>>>,
+}
+
+/// A stack of screens. Only the top screen gets input, logic and is drawn.
+pub struct ScreenStack<S, N>
+{
+	screens: Vec<Box<dyn Screen<S, N>>>,
+	transition_kind: TransitionKind,
+	transition_duration: f64,
+	transition: Option<Transition<S, N>>,
+	time: f64,
+}
+
+impl<S, N> ScreenStack<S, N>
+{
+	pub fn new() -> Self
+	{
+		Self {
+			screens: vec![],
+			transition_kind: TransitionKind::Cut,
+			transition_duration: 0.,
+			transition: None,
+			time: 0.,
+		}
+	}
+
+	/// Sets the transition used by `transition_to`.
+	pub fn set_transition(&mut self, kind: TransitionKind, duration: f64)
+	{
+		self.transition_kind = kind;
+		self.transition_duration = duration;
+	}
+
+	/// Like `replace`, but animated using the current transition.
+	pub fn transition_to(&mut self, screen: Box<dyn Screen<S, N>>)
+	{
+		if self.transition_kind == TransitionKind::Cut || self.transition_duration <= 0.
+		{
+			self.transition = None;
+			self.replace(screen);
+		}
+		else
+		{
+			self.transition = Some(Transition {
+				start_time: self.time,
+				next: Some(screen),
+			});
+		}
+	}
+
+	pub fn is_transitioning(&self) -> bool
+	{
+		self.transition.is_some()
+	}
+
+	/// Advances the current transition. Call this every frame with the current time.
+	pub fn update(&mut self, time: f64)
+	{
+		self.time = time;
+		let progress = self.transition_progress();
+		if let Some(transition) = &mut self.transition
+		{
+			if progress >= 0.5
+			{
+				if let Some(next) = transition.next.take()
+				{
+					self.screens.clear();
+					self.screens.push(next);
+				}
+			}
+			if progress >= 1.
+			{
+				self.transition = None;
+			}
+		}
+	}
+
+	fn transition_progress(&self) -> f64
+	{
+		match &self.transition
+		{
+			Some(transition) =>
+			{
+				((self.time - transition.start_time) / self.transition_duration).clamp(0., 1.)
+			}
+			None => 1.,
+		}
+	}
+
+	/// How much to darken the screen after `draw`, from 0 (not at all) to 1 (fully black).
+	pub fn fade(&self) -> f32
+	{
+		if self.transition.is_none()
+		{
+			return 0.;
+		}
+		match self.transition_kind
+		{
+			TransitionKind::Cut => 0.,
+			TransitionKind::FadeToBlack =>
+			{
+				(1. - (2. * self.transition_progress() - 1.).abs()) as f32
+			}
+		}
+	}
+
+	pub fn push(&mut self, screen: Box<dyn Screen<S, N>>)
+	{
+		self.screens.push(screen);
+	}
+
+	pub fn pop(&mut self) -> Option<Box<dyn Screen<S, N>>>
+	{
+		self.screens.pop()
+	}
+
+	/// Replaces the whole stack with `screen`.
+	pub fn replace(&mut self, screen: Box<dyn Screen<S, N>>)
+	{
+		self.screens.clear();
+		self.screens.push(screen);
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+		self.screens.is_empty()
+	}
+
+	pub fn len(&self) -> usize
+	{
+		self.screens.len()
+	}
+
+	/// Key and mouse button presses are dropped during transitions, so e.g. a button can't be
+	/// activated twice. Everything else, including releases, is still forwarded so nothing is
+	/// left held down.
+	pub fn input(&mut self, event: &Event, state: &mut S) -> Result<Option<N>>
+	{
+		if self.is_transitioning()
+		{
+			match event
+			{
+				Event::KeyDown { .. } | Event::KeyChar { .. } | Event::MouseButtonDown { .. } =>
+				{
+					return Ok(None);
+				}
+				_ => (),
+			}
+		}
+		match self.screens.last_mut()
+		{
+			Some(screen) => screen.input(event, state),
+			None => Ok(None),
+		}
+	}
+
+	/// Logic is paused during transitions.
+	pub fn logic(&mut self, state: &mut S) -> Result<Option<N>>
+	{
+		if self.is_transitioning()
+		{
+			return Ok(None);
+		}
+		match self.screens.last_mut()
+		{
+			Some(screen) => screen.logic(state),
+			None => Ok(None),
+		}
+	}
+
+	pub fn draw(&mut self, state: &S) -> Result<()>
+	{
+		match self.screens.last_mut()
+		{
+			Some(screen) => screen.draw(state),
+			None => Ok(()),
+		}
+	}
+
+	/// Resizes every screen, so the ones underneath are ready when they're uncovered.
+	pub fn resize(&mut self, state: &S)
+	{
+		for screen in &mut self.screens
+		{
+			screen.resize(state);
+		}
+		if let Some(next) = self.transition.as_mut().and_then(|t| t.next.as_mut())
+		{
+			next.resize(state);
+		}
+	}
+}
+
+#[test]
+fn test_screen_stack()
+{
+	struct Counter(i32);
+
+	impl Screen<Vec<i32>, i32> for Counter
+	{
+		fn input(&mut self, _event: &Event, state: &mut Vec<i32>) -> Result<Option<i32>>
+		{
+			state.push(-self.0);
+			Ok(None)
+		}
+
+		fn logic(&mut self, state: &mut Vec<i32>) -> Result<Option<i32>>
+		{
+			state.push(self.0);
+			Ok(Some(self.0))
+		}
+
+		fn draw(&mut self, _state: &Vec<i32>) -> Result<()>
+		{
+			Ok(())
+		}
+
+		fn resize(&mut self, _state: &Vec<i32>) {}
+	}
+
+	let mut state = vec![];
+	let mut stack = ScreenStack::new();
+	assert_eq!(stack.logic(&mut state).unwrap(), None);
+	stack.push(Box::new(Counter(1)));
+	stack.push(Box::new(Counter(2)));
+	assert_eq!(stack.logic(&mut state).unwrap(), Some(2));
+	stack.pop();
+	assert_eq!(stack.logic(&mut state).unwrap(), Some(1));
+	stack.push(Box::new(Counter(2)));
+	stack.replace(Box::new(Counter(3)));
+	assert_eq!(stack.len(), 1);
+	assert_eq!(stack.logic(&mut state).unwrap(), Some(3));
+	assert_eq!(state, vec![2, 1, 3]);
+
+	// Transitions.
+	state.clear();
+	let mut stack = ScreenStack::new();
+	stack.set_transition(TransitionKind::FadeToBlack, 1.);
+	stack.push(Box::new(Counter(1)));
+	stack.update(10.);
+	stack.transition_to(Box::new(Counter(2)));
+	assert!(stack.is_transitioning());
+	assert_eq!(stack.fade(), 0.);
+	assert_eq!(stack.logic(&mut state).unwrap(), None);
+
+	// Presses are dropped, releases still arrive.
+	let key_down = Event::KeyDown {
+		source: std::ptr::null_mut(),
+		timestamp: 0.,
+		keycode: KeyCode::Space,
+		display: std::ptr::null_mut(),
+	};
+	let key_up = Event::KeyUp {
+		source: std::ptr::null_mut(),
+		timestamp: 0.,
+		keycode: KeyCode::Space,
+		display: std::ptr::null_mut(),
+	};
+	stack.input(&key_down, &mut state).unwrap();
+	stack.input(&key_up, &mut state).unwrap();
+	assert_eq!(state, vec![-1]);
+	state.clear();
+
+	stack.update(10.25);
+	assert_eq!(stack.fade(), 0.5);
+	stack.update(10.5);
+	assert_eq!(stack.fade(), 1.);
+	stack.update(10.75);
+	assert_eq!(stack.fade(), 0.5);
+	assert_eq!(stack.len(), 1);
+	assert_eq!(stack.logic(&mut state).unwrap(), None);
+
+	stack.update(11.);
+	assert!(!stack.is_transitioning());
+	assert_eq!(stack.fade(), 0.);
+	assert_eq!(stack.logic(&mut state).unwrap(), Some(2));
+	assert_eq!(state, vec![2]);
+}
@@ -10,26 +10,224 @@ use allegro_audio::*;
 
 use rand::prelude::*;
 
+// Fraction of the output level retained per update, for the meter's decay.
+const LEVEL_DECAY: f32 = 0.9;
+// Default time to crossfade between music tracks, in seconds.
+const MUSIC_CROSSFADE_TIME: f32 = 1.;
+/// Time to fade out all audio when quitting, in seconds.
+pub const FADEOUT_TIME: f32 = 0.5;
+// Default voice limits, see `Sfx::set_voice_limits`.
+const MAX_INSTANCES: usize = 16;
+const MAX_VOICES: usize = 50;
+// Default random pitch variation of sound effects.
+const PITCH_VARIANCE: f32 = 0.1;
+
+/// Shape of the master volume fades, see `Sfx::fade_in`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FadeCurve
+{
+	Linear,
+	EaseInOut,
+	/// Changes the gain by a constant factor per unit time, which sounds even to the ear.
+	Exponential,
+}
+
+impl FadeCurve
+{
+	fn easing(self, fade_in: bool) -> fn(f32) -> f32
+	{
+		match self
+		{
+			FadeCurve::Linear => utils::ease_linear,
+			FadeCurve::EaseInOut => utils::ease_in_out_quad,
+			FadeCurve::Exponential =>
+			{
+				if fade_in
+				{
+					utils::ease_in_expo
+				}
+				else
+				{
+					utils::ease_out_expo
+				}
+			}
+		}
+	}
+}
+
+/// Identifies a playing ambience stream, see `Sfx::play_ambience`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AmbienceId(usize);
+
+struct Ambience<T>
+{
+	id: AmbienceId,
+	stream: T,
+	volume: f32,
+}
+
+/// The ambience streams and their ids. Generic over the stream so the bookkeeping can be tested
+/// without an audio device.
+struct AmbienceList<T>
+{
+	entries: Vec<Ambience<T>>,
+	next_id: usize,
+}
+
+impl<T> AmbienceList<T>
+{
+	fn new() -> Self
+	{
+		Self {
+			entries: vec![],
+			next_id: 0,
+		}
+	}
+
+	fn add(&mut self, stream: T, volume: f32) -> AmbienceId
+	{
+		let id = AmbienceId(self.next_id);
+		self.next_id += 1;
+		self.entries.push(Ambience {
+			id: id,
+			stream: stream,
+			volume: volume,
+		});
+		id
+	}
+
+	fn remove(&mut self, id: AmbienceId) -> Option<T>
+	{
+		let idx = self.entries.iter().position(|a| a.id == id)?;
+		Some(self.entries.remove(idx).stream)
+	}
+
+	fn get(&self, id: AmbienceId) -> Option<&T>
+	{
+		self.entries.iter().find(|a| a.id == id).map(|a| &a.stream)
+	}
+}
+
 pub struct Sfx
 {
 	audio: AudioAddon,
 	acodec: AcodecAddon,
 	sink: Sink,
 	stream: Option<AudioStream>,
+	/// The previous music track while it's being crossfaded out, with its starting gain.
+	old_stream: Option<(AudioStream, f32)>,
+	crossfade_time: f32,
+	crossfade_progress: f32,
+	ambience: AmbienceList<AudioStream>,
 	music_file: String,
 	music_volume_factor: f32,
-	sample_instances: Vec<SampleInstance>,
+	playlist: Vec<String>,
+	playlist_index: usize,
+	/// Playing one-shot sounds with their sample names, oldest first.
+	sample_instances: Vec<(String, SampleInstance)>,
+	max_instances: usize,
+	max_voices: usize,
 	exclusive_sounds: Vec<String>,
 	exclusive_instance: Option<SampleInstance>,
 	sfx_volume: f32,
 	music_volume: f32,
+	rng: StdRng,
+	pitch_variance: f32,
+	level: f32,
+	paused: bool,
+	master_fade: Option<utils::Tween<f32>>,
 
 	samples: HashMap<String, Sample>,
 }
 
+// Overall loudness of positional sounds, relative to `PositionalParams::base_dist`.
+const POSITIONAL_GAIN: f32 = 40.;
+
+/// How positional sounds fade and pan with distance from the listener.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PositionalParams
+{
+	/// Distance scale for the attenuation and the panning.
+	pub base_dist: f32,
+	/// Sounds further than this are not played.
+	pub max_dist: f32,
+	/// Attenuation exponent, 2 is the inverse-square law.
+	pub rolloff: f32,
+}
+
+impl Default for PositionalParams
+{
+	fn default() -> Self
+	{
+		Self {
+			base_dist: 100.,
+			max_dist: f32::INFINITY,
+			rolloff: 2.,
+		}
+	}
+}
+
+impl PositionalParams
+{
+	/// Returns the attenuation and pan for a sound at `diff` relative to the listener, or `None`
+	/// if it's out of range.
+	fn attenuation_and_pan(&self, diff: Vector2<f32>) -> Option<(f32, f32)>
+	{
+		let dist = diff.norm();
+		if dist > self.max_dist
+		{
+			return None;
+		}
+		let attenuation = POSITIONAL_GAIN * (self.base_dist / dist).powf(self.rolloff);
+		let pan = diff.x / (diff.x.powf(2.) + self.base_dist.powf(2.)).sqrt();
+		Some((attenuation, pan))
+	}
+}
+
+/// Speed of sound for the doppler effect, in world units per second. This is the speed in air,
+/// assuming one world unit is a meter.
+const SPEED_OF_SOUND: f32 = 343.;
+
+/// Pitch multiplier for a sound at `diff` relative to the listener. Velocities are in world units
+/// per second.
+fn doppler_pitch(diff: Vector2<f32>, source_vel: Vector2<f32>, listener_vel: Vector2<f32>) -> f32
+{
+	let dist = diff.norm();
+	if dist == 0.
+	{
+		return 1.;
+	}
+	// Unit vector from the source to the listener.
+	let dir = -diff / dist;
+	let max_speed = 0.9 * SPEED_OF_SOUND;
+	let listener_speed = utils::clamp(-listener_vel.dot(&dir), -max_speed, max_speed);
+	let source_speed = utils::clamp(source_vel.dot(&dir), -max_speed, max_speed);
+	(SPEED_OF_SOUND + listener_speed) / (SPEED_OF_SOUND - source_speed)
+}
+
+fn random_pitch<R: Rng>(rng: &mut R, variance: f32) -> f32
+{
+	if variance > 0.
+	{
+		rng.gen_range(1. - variance..1. + variance)
+	}
+	else
+	{
+		1.
+	}
+}
+
+/// The meter level given the gains of the playing sounds: their sum, or `previous` scaled by
+/// `decay` if that's louder, so the meter falls off smoothly.
+fn meter_level(previous: f32, gains: &[f32], decay: f32) -> f32
+{
+	let current = utils::clamp(gains.iter().sum(), 0., 1.);
+	utils::max(current, previous * decay)
+}
+
 impl Sfx
 {
-	pub fn new(sfx_volume: f32, music_volume: f32, core: &Core) -> Result<Sfx>
+	pub fn new(sfx_volume: f32, music_volume: f32, seed: u64, core: &Core) -> Result<Sfx>
 	{
 		let audio = AudioAddon::init(&core)?;
 		let acodec = AcodecAddon::init(&audio)?;
@@ -42,12 +240,25 @@ impl Sfx
 			acodec: acodec,
 			sink: sink,
 			sample_instances: vec![],
+			max_instances: MAX_INSTANCES,
+			max_voices: MAX_VOICES,
 			stream: None,
+			old_stream: None,
+			crossfade_time: MUSIC_CROSSFADE_TIME,
+			crossfade_progress: 1.,
+			ambience: AmbienceList::new(),
 			exclusive_instance: None,
 			exclusive_sounds: vec![],
 			samples: HashMap::new(),
 			music_file: "".into(),
 			music_volume_factor: 1.0,
+			playlist: vec![],
+			playlist_index: 0,
+			rng: StdRng::seed_from_u64(seed),
+			pitch_variance: PITCH_VARIANCE,
+			level: 0.,
+			paused: false,
+			master_fade: None,
 		};
 		sfx.set_sfx_volume(sfx_volume);
 		sfx.set_music_volume(music_volume);
@@ -61,6 +272,78 @@ impl Sfx
 		self.music_volume_factor = music_volume_factor;
 	}
 
+	/// Sets tracks to play in sequence, looping back to the first one after the last. Call
+	/// `play_music` to start it. An empty playlist reverts to looping the single music file.
+	pub fn set_playlist(&mut self, playlist: Vec<String>)
+	{
+		self.playlist = playlist;
+		self.playlist_index = 0;
+		if let Some(track) = self.playlist.first()
+		{
+			self.music_file = track.clone();
+		}
+	}
+
+	/// The music file that's currently playing, if any.
+	pub fn current_music(&self) -> Option<&str>
+	{
+		self.stream.as_ref().map(|_| &self.music_file[..])
+	}
+
+	/// Sets how long `play_music` takes to crossfade from the previous track. 0 switches
+	/// instantly.
+	pub fn set_music_crossfade_time(&mut self, crossfade_time: f32)
+	{
+		self.crossfade_time = crossfade_time;
+	}
+
+	/// Sets how many one-shot sounds can play at once, both per sample and in total. Past the
+	/// per-sample limit new sounds are dropped, past the total the oldest sound is stopped.
+	///
+	/// The defaults are 16 per sample and 50 in total. Before these limits existed every
+	/// `play_sound` call played, so a sound triggered more than 16 times in quick succession is
+	/// now partly silent; raise `max_instances` if that's not wanted.
+	pub fn set_voice_limits(&mut self, max_instances: usize, max_voices: usize)
+	{
+		self.max_instances = max_instances;
+		self.max_voices = max_voices;
+	}
+
+	/// Sets how much the pitch of sound effects randomly varies, e.g. 0.1 for +-10%.
+	pub fn set_pitch_variance(&mut self, pitch_variance: f32)
+	{
+		self.pitch_variance = pitch_variance;
+	}
+
+	/// Forgets the one-shot sounds that have finished playing.
+	fn prune_instances(&mut self)
+	{
+		self.sample_instances
+			.retain(|(_, s)| s.get_playing().unwrap_or(false));
+	}
+
+	/// Returns whether a new instance of `name` can be played, stopping the oldest instances if
+	/// needed to stay under the voice limit.
+	fn make_room(&mut self, name: &str) -> bool
+	{
+		self.prune_instances();
+		let num_instances = self
+			.sample_instances
+			.iter()
+			.filter(|(n, _)| n == name)
+			.count();
+		if num_instances >= self.max_instances || self.max_voices == 0
+		{
+			return false;
+		}
+		while self.sample_instances.len() >= self.max_voices
+		{
+			let (_, instance) = self.sample_instances.remove(0);
+			let _ = instance.set_playing(false);
+		}
+		true
+	}
+
 	pub fn cache_sample<'l>(&'l mut self, name: &str) -> Result<&'l Sample>
 	{
 		Ok(match self.samples.entry(name.to_string())
@@ -75,13 +358,78 @@ impl Sfx
 		self.samples.get(name)
 	}
 
+	/// Approximate output level in [0, 1], for VU-style meters. This is derived from the gains of
+	/// the playing sounds rather than from the actual output samples.
+	pub fn output_level(&self) -> f32
+	{
+		meter_level(self.level, &self.playing_gains(), 1.)
+	}
+
+	/// Gains of everything that's currently playing.
+	fn playing_gains(&self) -> Vec<f32>
+	{
+		let mut gains = vec![];
+		for (_, instance) in &self.sample_instances
+		{
+			if instance.get_playing().unwrap_or(false)
+			{
+				gains.push(instance.get_gain().unwrap_or(0.));
+			}
+		}
+		if let Some(instance) = &self.exclusive_instance
+		{
+			if instance.get_playing().unwrap_or(false)
+			{
+				gains.push(instance.get_gain().unwrap_or(0.));
+			}
+		}
+		for ambience in &self.ambience.entries
+		{
+			if ambience.stream.get_playing()
+			{
+				gains.push(ambience.stream.get_gain());
+			}
+		}
+		if let Some(stream) = &self.stream
+		{
+			if stream.get_playing()
+			{
+				gains.push(stream.get_gain());
+			}
+		}
+		if let Some((stream, _)) = &self.old_stream
+		{
+			if stream.get_playing()
+			{
+				gains.push(stream.get_gain());
+			}
+		}
+		gains
+	}
+
 	pub fn update_sounds(&mut self) -> Result<()>
 	{
-		self.sample_instances.retain(|s| s.get_playing().unwrap());
+		self.update_fade();
+		if self.paused
+		{
+			return Ok(());
+		}
+		self.prune_instances();
+		self.level = meter_level(self.level, &self.playing_gains(), LEVEL_DECAY);
+		if self.old_stream.is_some()
+		{
+			self.crossfade_progress += utils::DT / self.crossfade_time;
+			self.update_music_gain();
+		}
 		if let Some(ref stream) = self.stream
 		{
 			if !stream.get_playing()
 			{
+				if !self.playlist.is_empty()
+				{
+					self.playlist_index = (self.playlist_index + 1) % self.playlist.len();
+					self.music_file = self.playlist[self.playlist_index].clone();
+				}
 				self.play_music()?
 			}
 		}
@@ -104,7 +452,7 @@ impl Sfx
 						sample,
 						self.sfx_volume,
 						None,
-						thread_rng().gen_range(0.9..1.1),
+						random_pitch(&mut self.rng, self.pitch_variance),
 						Playmode::Once,
 					)
 					.map_err(|_| "Couldn't play sound".to_string())?;
@@ -117,40 +465,38 @@ impl Sfx
 
 	pub fn play_sound_with_pitch(&mut self, name: &str, pitch: f32) -> Result<()>
 	{
-		self.cache_sample(name)?;
-		let sample = self.samples.get(name).unwrap();
-		let instance = self
-			.sink
-			.play_sample(
-				sample,
-				self.sfx_volume,
-				None,
-				thread_rng().gen_range(0.9..1.1) * pitch,
-				Playmode::Once,
-			)
-			.map_err(|_| "Couldn't play sound".to_string())?;
-		self.sample_instances.push(instance);
-		Ok(())
+		let pitch = random_pitch(&mut self.rng, self.pitch_variance) * pitch;
+		self.play_sound_with_pitch_exact(name, pitch)
 	}
 
 	pub fn play_sound(&mut self, name: &str) -> Result<()>
+	{
+		self.play_sound_with_pitch(name, 1.)
+	}
+
+	/// Like `play_sound_with_pitch`, but without the random pitch variance.
+	pub fn play_sound_with_pitch_exact(&mut self, name: &str, pitch: f32) -> Result<()>
 	{
 		self.cache_sample(name)?;
+		if !self.make_room(name)
+		{
+			return Ok(());
+		}
 		let sample = self.samples.get(name).unwrap();
 		let instance = self
 			.sink
-			.play_sample(
-				sample,
-				self.sfx_volume,
-				None,
-				thread_rng().gen_range(0.9..1.1),
-				Playmode::Once,
-			)
+			.play_sample(sample, self.sfx_volume, None, pitch, Playmode::Once)
 			.map_err(|_| "Couldn't play sound".to_string())?;
-		self.sample_instances.push(instance);
+		self.sample_instances.push((name.to_string(), instance));
 		Ok(())
 	}
 
+	/// Like `play_sound`, but without the random pitch variance.
+	pub fn play_sound_exact(&mut self, name: &str) -> Result<()>
+	{
+		self.play_sound_with_pitch_exact(name, 1.)
+	}
+
 	pub fn play_continuous_sound(&mut self, name: &str, volume: f32) -> Result<SampleInstance>
 	{
 		self.cache_sample(name)?;
@@ -165,19 +511,52 @@ impl Sfx
 	pub fn play_positional_sound(
 		&mut self, name: &str, sound_pos: Point2<f32>, camera_pos: Point2<f32>, volume: f32,
 	) -> Result<()>
+	{
+		self.play_positional_sound_with_params(
+			name,
+			sound_pos,
+			camera_pos,
+			volume,
+			&PositionalParams::default(),
+		)
+	}
+
+	pub fn play_positional_sound_with_params(
+		&mut self, name: &str, sound_pos: Point2<f32>, camera_pos: Point2<f32>, volume: f32,
+		params: &PositionalParams,
+	) -> Result<()>
+	{
+		self.play_positional_sound_doppler(
+			name,
+			sound_pos,
+			Vector2::zeros(),
+			camera_pos,
+			Vector2::zeros(),
+			volume,
+			params,
+		)
+	}
+
+	/// Like `play_positional_sound_with_params`, but also shifts the pitch according to the
+	/// source and listener velocities.
+	pub fn play_positional_sound_doppler(
+		&mut self, name: &str, sound_pos: Point2<f32>, sound_vel: Vector2<f32>,
+		camera_pos: Point2<f32>, camera_vel: Vector2<f32>, volume: f32, params: &PositionalParams,
+	) -> Result<()>
 	{
 		self.cache_sample(name)?;
 
-		if self.sample_instances.len() < 50
+		let (attenuation, pan) = match params.attenuation_and_pan(sound_pos - camera_pos)
+		{
+			Some(v) => v,
+			None => return Ok(()),
+		};
+		if self.make_room(name)
 		{
 			let sample = self.samples.get(name).unwrap();
 
-			let dist_sq = (sound_pos - camera_pos).norm_squared();
-			let volume = self.sfx_volume
-				* utils::clamp(self.sfx_volume * volume * 400000. / dist_sq, 0., 1.);
-			println!("volume: {}", volume);
-			let diff = sound_pos - camera_pos;
-			let pan = diff.x / (diff.x.powf(2.) + 100.0_f32.powf(2.)).sqrt();
+			let volume =
+				self.sfx_volume * utils::clamp(self.sfx_volume * volume * attenuation, 0., 1.);
 
 			let instance = self
 				.sink
@@ -185,45 +564,299 @@ impl Sfx
 					sample,
 					volume,
 					Some(pan),
-					thread_rng().gen_range(0.9..1.1),
+					random_pitch(&mut self.rng, self.pitch_variance)
+						* doppler_pitch(sound_pos - camera_pos, sound_vel, camera_vel),
 					Playmode::Once,
 				)
 				.map_err(|_| "Couldn't play sound".to_string())?;
-			self.sample_instances.push(instance);
+			self.sample_instances.push((name.to_string(), instance));
 		}
 		Ok(())
 	}
 
+	pub fn stop_instance(&self, instance: &SampleInstance)
+	{
+		let _ = instance.set_playing(false);
+	}
+
+	/// Pauses all audio output, including the music.
+	pub fn pause_all(&mut self)
+	{
+		self.paused = true;
+		let _ = self.sink.set_voice_playing(false);
+	}
+
+	pub fn resume_all(&mut self)
+	{
+		self.paused = false;
+		let _ = self.sink.set_voice_playing(true);
+	}
+
+	pub fn is_paused(&self) -> bool
+	{
+		self.paused
+	}
+
 	pub fn play_exclusive_sound(&mut self, name: &str) -> Result<()>
 	{
 		self.exclusive_sounds.insert(0, name.to_string());
 		Ok(())
 	}
 
+	/// Starts playing the current music file, crossfading from the previous track if there is
+	/// one.
 	pub fn play_music(&mut self) -> Result<()>
 	{
 		let mut new_stream = AudioStream::load(&self.audio, &self.music_file)
 			.map_err(|_| format!("Couldn't load {}", self.music_file))?;
 		new_stream.attach(&mut self.sink).unwrap();
-		new_stream.set_playmode(Playmode::Loop).unwrap();
-		new_stream
-			.set_gain(self.music_volume * self.music_volume_factor)
-			.unwrap();
-		self.stream = Some(new_stream);
+		// With a playlist, let the track end so `update_sounds` can advance to the next one.
+		let playmode = if self.playlist.is_empty()
+		{
+			Playmode::Loop
+		}
+		else
+		{
+			Playmode::Once
+		};
+		new_stream.set_playmode(playmode).unwrap();
+		let old_stream = self.stream.replace(new_stream);
+		self.old_stream = None;
+		self.crossfade_progress = 1.;
+		if let Some(old_stream) = old_stream
+		{
+			if self.crossfade_time > 0. && old_stream.get_playing()
+			{
+				let gain = old_stream.get_gain();
+				self.old_stream = Some((old_stream, gain));
+				self.crossfade_progress = 0.;
+			}
+		}
+		self.update_music_gain();
 		Ok(())
 	}
 
-	pub fn set_music_volume(&mut self, new_volume: f32)
+	/// Fades all audio in from silence over `duration` seconds.
+	pub fn fade_in(&mut self, duration: f32, curve: FadeCurve)
 	{
-		self.music_volume = new_volume;
+		self.sink.set_gain(0.).unwrap();
+		self.master_fade = Some(utils::Tween::new(0., 1., duration, curve.easing(true)));
+	}
+
+	/// Fades all audio out to silence over `duration` seconds. The fade progresses in
+	/// `update_sounds`.
+	pub fn start_fade_out(&mut self, duration: f32, curve: FadeCurve)
+	{
+		self.master_fade = Some(utils::Tween::new(
+			self.sink.get_gain(),
+			0.,
+			duration,
+			curve.easing(false),
+		));
+	}
+
+	/// Whether a fade out started by `start_fade_out` has finished.
+	pub fn fade_out_complete(&self) -> bool
+	{
+		self.master_fade.is_none() && self.sink.get_gain() == 0.
+	}
+
+	/// Like `start_fade_out`, but blocks until the fade is done. Prefer `start_fade_out` when
+	/// there's an event loop to keep running.
+	pub fn fade_out(&mut self, core: &Core, duration: f32, curve: FadeCurve)
+	{
+		self.start_fade_out(duration, curve);
+		while !self.fade_out_complete()
+		{
+			core.rest(utils::DT as f64);
+			self.update_fade();
+		}
+	}
+
+	fn update_fade(&mut self)
+	{
+		if let Some(fade) = self.master_fade.as_mut()
+		{
+			fade.update(utils::DT);
+			self.sink.set_gain(fade.value()).unwrap();
+			if fade.finished()
+			{
+				self.master_fade = None;
+			}
+		}
+	}
+
+	/// Stops the music immediately, without fading.
+	pub fn stop_music(&mut self)
+	{
+		self.stream = None;
+		self.old_stream = None;
+		self.crossfade_progress = 1.;
+	}
+
+	fn update_music_gain(&mut self)
+	{
+		let f = utils::clamp(self.crossfade_progress, 0., 1.);
 		if let Some(stream) = self.stream.as_mut()
 		{
-			stream.set_gain(self.music_volume).unwrap();
+			stream
+				.set_gain(f * self.music_volume * self.music_volume_factor)
+				.unwrap();
+		}
+		if let Some((stream, gain)) = self.old_stream.as_mut()
+		{
+			stream.set_gain((1. - f) * *gain).unwrap();
 		}
+		if f >= 1.
+		{
+			self.old_stream = None;
+		}
+	}
+
+	/// Plays a long, looping sound (e.g. rain) by streaming it from disk rather than loading it
+	/// as a sample. Multiple ambience streams can play alongside the music.
+	pub fn play_ambience(&mut self, name: &str, volume: f32) -> Result<AmbienceId>
+	{
+		let mut stream =
+			AudioStream::load(&self.audio, name).map_err(|_| format!("Couldn't load {}", name))?;
+		stream
+			.attach(&mut self.sink)
+			.map_err(|_| format!("Couldn't attach {}", name))?;
+		stream.set_playmode(Playmode::Loop).unwrap();
+		stream.set_gain(self.sfx_volume * volume).unwrap();
+		Ok(self.ambience.add(stream, volume))
+	}
+
+	pub fn stop_ambience(&mut self, id: AmbienceId)
+	{
+		self.ambience.remove(id);
+	}
+
+	pub fn is_ambience_playing(&self, id: AmbienceId) -> bool
+	{
+		self.ambience
+			.get(id)
+			.map(|s| s.get_playing())
+			.unwrap_or(false)
+	}
+
+	pub fn set_music_volume(&mut self, new_volume: f32)
+	{
+		self.music_volume = new_volume;
+		self.update_music_gain();
 	}
 
 	pub fn set_sfx_volume(&mut self, new_volume: f32)
 	{
 		self.sfx_volume = new_volume;
+		for ambience in &mut self.ambience.entries
+		{
+			ambience
+				.stream
+				.set_gain(self.sfx_volume * ambience.volume)
+				.unwrap();
+		}
+	}
+}
+
+#[test]
+fn test_seeded_pitch()
+{
+	let mut game_rng1 = utils::make_rng(Some(1234));
+	let mut game_rng2 = utils::make_rng(Some(1234));
+	let mut rng1 = StdRng::seed_from_u64(game_rng1.gen());
+	let mut rng2 = StdRng::seed_from_u64(game_rng2.gen());
+	for _ in 0..10
+	{
+		assert_eq!(random_pitch(&mut rng1, 0.1), random_pitch(&mut rng2, 0.1));
+	}
+}
+
+#[test]
+fn test_positional_params()
+{
+	let params = PositionalParams::default();
+	let (attenuation, pan) = params
+		.attenuation_and_pan(Vector2::new(300., 400.))
+		.unwrap();
+	assert!((attenuation - 400000. / 250000.).abs() < 1e-5);
+	assert!((pan - 300. / (300.0f32.powf(2.) + 100.0f32.powf(2.)).sqrt()).abs() < 1e-5);
+
+	let params = PositionalParams {
+		max_dist: 400.,
+		..params
+	};
+	assert_eq!(params.attenuation_and_pan(Vector2::new(300., 400.)), None);
+}
+
+#[test]
+fn test_doppler_pitch()
+{
+	let diff = Vector2::new(100., 0.);
+	assert_eq!(doppler_pitch(diff, Vector2::zeros(), Vector2::zeros()), 1.);
+	// Approaching sounds are higher pitched, receding ones lower.
+	assert!(doppler_pitch(diff, Vector2::new(-10., 0.), Vector2::zeros()) > 1.);
+	assert!(doppler_pitch(diff, Vector2::new(10., 0.), Vector2::zeros()) < 1.);
+	assert!(doppler_pitch(diff, Vector2::zeros(), Vector2::new(10., 0.)) > 1.);
+	// Perpendicular motion doesn't shift the pitch.
+	assert_eq!(
+		doppler_pitch(diff, Vector2::new(0., 10.), Vector2::zeros()),
+		1.
+	);
+}
+
+#[test]
+fn test_zero_pitch_variance()
+{
+	let mut rng = StdRng::seed_from_u64(0);
+	assert_eq!(random_pitch(&mut rng, 0.), 1.);
+}
+
+#[test]
+fn test_ambience_list()
+{
+	// Actually playing a stream needs an audio device, so this only covers the bookkeeping that
+	// `play_ambience` and `stop_ambience` do on top of it.
+	let mut ambience = AmbienceList::new();
+	let rain = ambience.add("rain", 0.5);
+	let wind = ambience.add("wind", 1.);
+	assert_ne!(rain, wind);
+	assert_eq!(ambience.get(rain), Some(&"rain"));
+	assert_eq!(ambience.get(wind), Some(&"wind"));
+
+	assert_eq!(ambience.remove(rain), Some("rain"));
+	assert_eq!(ambience.get(rain), None);
+	assert_eq!(ambience.remove(rain), None);
+	assert_eq!(ambience.get(wind), Some(&"wind"));
+
+	// Ids aren't reused, so a stale id can't stop a newer stream.
+	let fire = ambience.add("fire", 1.);
+	assert_ne!(fire, rain);
+	assert_eq!(ambience.entries.len(), 2);
+}
+
+#[test]
+fn test_meter_level()
+{
+	let mut level = 0.;
+	level = meter_level(level, &[0.25, 0.5], LEVEL_DECAY);
+	assert_eq!(level, 0.75);
+	// Clamped to 1.
+	level = meter_level(level, &[0.75, 0.5], LEVEL_DECAY);
+	assert_eq!(level, 1.);
+
+	// Once the sounds stop, the level decays rather than dropping to 0.
+	level = meter_level(level, &[], LEVEL_DECAY);
+	assert_eq!(level, LEVEL_DECAY);
+	let prev = level;
+	level = meter_level(level, &[0.1], LEVEL_DECAY);
+	assert_eq!(level, prev * LEVEL_DECAY);
+	for _ in 0..100
+	{
+		level = meter_level(level, &[], LEVEL_DECAY);
 	}
+	assert!(level < 0.01);
+
+	// Without decay, the level holds.
+	assert_eq!(meter_level(0.5, &[], 1.), 0.5);
 }
@@ -2,10 +2,21 @@ use crate::error::Result;
 use crate::game_state::GameState;
 use crate::{atlas, utils};
 use allegro::*;
-use na::Point2;
+use na::{Point2, Vector2};
 use nalgebra as na;
 use serde_derive::{Deserialize, Serialize};
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Playback
+{
+	#[default]
+	Loop,
+	/// Play once and stop on the last frame.
+	Once,
+	/// Play forward, then backward, and repeat.
+	PingPong,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 struct SpriteDesc
 {
@@ -17,6 +28,11 @@ struct SpriteDesc
 	center_x: i32,
 	#[serde(default)]
 	center_y: i32,
+	/// Strip fully transparent borders from each variant before inserting it into the atlas.
+	#[serde(default)]
+	trim: bool,
+	#[serde(default)]
+	playback: Playback,
 }
 
 #[derive(Clone, Debug)]
@@ -24,6 +40,113 @@ pub struct Sprite
 {
 	desc: SpriteDesc,
 	variants: Vec<atlas::AtlasBitmap>,
+	/// Offset of each variant's atlas region within its untrimmed frame.
+	offsets: Vec<Vector2<f32>>,
+}
+
+/// Returns the variant to show for the `frame`th animation frame, and the number of completed loops.
+/// For `PingPong`, a loop is a full forward and backward pass.
+fn playback_frame(frame: i64, num_variants: i64, playback: Playback) -> (i32, i32)
+{
+	let (variant, loops) = match playback
+	{
+		Playback::Loop => (frame % num_variants, frame / num_variants),
+		Playback::Once =>
+		{
+			if frame >= num_variants
+			{
+				(num_variants - 1, 1)
+			}
+			else
+			{
+				(frame, 0)
+			}
+		}
+		Playback::PingPong =>
+		{
+			if num_variants == 1
+			{
+				(0, frame)
+			}
+			else
+			{
+				let period = 2 * num_variants - 2;
+				let f = frame % period;
+				(
+					if f < num_variants { f } else { period - f },
+					frame / period,
+				)
+			}
+		}
+	};
+	(variant as i32, loops as i32)
+}
+
+/// Returns how far through the clip `frames` (fractional frames elapsed) is, in 0..1.
+fn playback_progress(frames: f64, num_variants: i64, playback: Playback) -> f32
+{
+	if num_variants <= 1
+	{
+		return 0.;
+	}
+	let clip_frames = match playback
+	{
+		Playback::Loop | Playback::Once => num_variants,
+		Playback::PingPong => 2 * num_variants - 2,
+	} as f64;
+	let progress = match playback
+	{
+		Playback::Once => utils::min(frames / clip_frames, 1.),
+		_ => frames.rem_euclid(clip_frames) / clip_frames,
+	};
+	progress as f32
+}
+
+/// Computes the bounding rectangle (x, y, w, h) of the opaque pixels in a w x h frame. Returns
+/// `None` if the frame is fully transparent.
+fn trim_rect<F: Fn(i32, i32) -> bool>(w: i32, h: i32, is_opaque: F)
+	-> Option<(i32, i32, i32, i32)>
+{
+	let mut min_x = w;
+	let mut min_y = h;
+	let mut max_x = -1;
+	let mut max_y = -1;
+	for y in 0..h
+	{
+		for x in 0..w
+		{
+			if is_opaque(x, y)
+			{
+				min_x = utils::min(min_x, x);
+				min_y = utils::min(min_y, y);
+				max_x = utils::max(max_x, x);
+				max_y = utils::max(max_y, y);
+			}
+		}
+	}
+	if max_x < 0
+	{
+		None
+	}
+	else
+	{
+		Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+	}
+}
+
+/// The part (x, y, w, h) of a frame that goes into the atlas: the opaque pixels if `desc.trim` is
+/// set, otherwise the whole frame.
+fn frame_rect<F: Fn(i32, i32) -> bool>(desc: &SpriteDesc, is_opaque: F) -> (i32, i32, i32, i32)
+{
+	let full = (0, 0, desc.width, desc.height);
+	if desc.trim
+	{
+		trim_rect(desc.width, desc.height, is_opaque).unwrap_or(full)
+	}
+	else
+	{
+		full
+	}
 }
 
 impl Sprite
@@ -32,36 +155,46 @@ impl Sprite
 	{
 		let desc: SpriteDesc = utils::load_config(sprite)?;
 
-		let bitmap = utils::load_bitmap(&core, &desc.bitmap)?;
+		let old_flags = core.get_new_bitmap_flags();
+		if desc.trim
+		{
+			// Reading pixels from video bitmaps is slow.
+			core.set_new_bitmap_flags(MEMORY_BITMAP);
+		}
+		let bitmap = utils::load_bitmap(&core, &desc.bitmap);
+		core.set_new_bitmap_flags(old_flags);
+		let bitmap = bitmap?;
 
 		let num_variants_y = bitmap.get_height() / desc.height;
 		let num_variants_x = bitmap.get_width() / desc.width;
 		let num_variants = num_variants_x * num_variants_y;
 		let mut variants = Vec::with_capacity(num_variants as usize);
+		let mut offsets = Vec::with_capacity(num_variants as usize);
 		for y in 0..num_variants_y
 		{
 			for x in 0..num_variants_x
 			{
+				let (start_x, start_y) = (x * desc.width, y * desc.height);
+				let (dx, dy, w, h) = frame_rect(&desc, |px, py| {
+					bitmap.get_pixel(start_x + px, start_y + py).to_rgba_f().3 > 0.
+				});
 				variants.push(
 					atlas.insert(
 						&core,
 						&*bitmap
-							.create_sub_bitmap(
-								x * desc.width,
-								y * desc.height,
-								desc.width,
-								desc.height,
-							)
+							.create_sub_bitmap(start_x + dx, start_y + dy, w, h)
 							.map_err(|_| "Couldn't create sub-bitmap?".to_string())?
 							.upgrade()
 							.unwrap(),
 					)?,
-				)
+				);
+				offsets.push(Vector2::new(dx as f32, dy as f32));
 			}
 		}
 		Ok(Sprite {
 			desc: desc,
 			variants: variants,
+			offsets: offsets,
 		})
 	}
 
@@ -70,26 +203,110 @@ impl Sprite
 		self.variants.len() as i32
 	}
 
+	fn get_frame(&self, time: f64) -> i64
+	{
+		(time * self.desc.frame_rate as f64) as i64
+	}
+
 	pub fn get_variant(&self, time: f64) -> i32
 	{
-		((time * self.desc.frame_rate as f64) % (self.num_variants() as f64)) as i32
+		playback_frame(
+			self.get_frame(time),
+			self.num_variants() as i64,
+			self.desc.playback,
+		)
+		.0
 	}
 
-	pub fn draw(&self, pos: Point2<f32>, variant: i32, tint: Color, state: &GameState)
+	/// Number of times the animation has completed by `time`.
+	pub fn get_num_loops(&self, time: f64) -> i32
+	{
+		playback_frame(
+			self.get_frame(time),
+			self.num_variants() as i64,
+			self.desc.playback,
+		)
+		.1
+	}
+
+	/// How far through the whole animation clip we are at `time`, in 0..1.
+	pub fn get_progress(&self, time: f64) -> f32
+	{
+		if self.desc.frame_rate <= 0.
+		{
+			return 0.;
+		}
+		playback_progress(
+			time * self.desc.frame_rate as f64,
+			self.num_variants() as i64,
+			self.desc.playback,
+		)
+	}
+
+	/// Time spent in the current frame at `time`, in seconds.
+	pub fn get_frame_time(&self, time: f64) -> f64
+	{
+		if self.desc.frame_rate <= 0. || self.num_variants() <= 1 || self.is_done(time)
+		{
+			return 0.;
+		}
+		(time * self.desc.frame_rate as f64).fract() / self.desc.frame_rate as f64
+	}
+
+	/// Whether a `Once` animation has finished by `time`. Always false for the other modes.
+	pub fn is_done(&self, time: f64) -> bool
+	{
+		self.desc.playback == Playback::Once && self.get_num_loops(time) > 0
+	}
+
+	/// Returns the atlas page of a variant, its normalized UV rectangle within that page as
+	/// `[u_start, v_start, u_end, v_end]`, and the pixel offset of that rectangle within the
+	/// untrimmed frame. The offset is zero unless the sprite is trimmed, and should be added to
+	/// the frame's top left corner when drawing the rectangle.
+	pub fn frame_uv_rect(
+		&self, variant: i32, atlas: &atlas::Atlas,
+	) -> (usize, [f32; 4], Vector2<f32>)
+	{
+		let atlas_bmp = &self.variants[variant as usize];
+		let size = atlas.page_size() as f32;
+		(
+			atlas_bmp.page,
+			[
+				atlas_bmp.start.x / size,
+				atlas_bmp.start.y / size,
+				atlas_bmp.end.x / size,
+				atlas_bmp.end.y / size,
+			],
+			self.offsets[variant as usize],
+		)
+	}
+
+	/// The point within a variant's atlas region that's drawn at the sprite's position.
+	fn pivot(&self, variant: i32) -> Vector2<f32>
 	{
 		let w = self.desc.width as f32;
 		let h = self.desc.height as f32;
+		let offset = self.offsets[variant as usize];
+		Vector2::new(
+			self.desc.center_x as f32 + w / 2. - offset.x,
+			self.desc.center_y as f32 + h / 2. - offset.y,
+		)
+	}
+
+	pub fn draw(&self, pos: Point2<f32>, variant: i32, tint: Color, state: &GameState)
+	{
 		let atlas_bmp = &self.variants[variant as usize];
+		let pivot = self.pivot(variant);
 
 		state.core.draw_tinted_bitmap_region(
 			&state.atlas.pages[atlas_bmp.page].bitmap,
 			tint,
 			atlas_bmp.start.x,
 			atlas_bmp.start.y,
-			w,
-			h,
-			pos.x - self.desc.center_x as f32 - w / 2.,
-			pos.y - self.desc.center_y as f32 - h / 2.,
+			atlas_bmp.width(),
+			atlas_bmp.height(),
+			pos.x - pivot.x,
+			pos.y - pivot.y,
 			Flag::zero(),
 		);
 	}
@@ -98,25 +315,166 @@ impl Sprite
 		&self, pos: Point2<f32>, variant: i32, tint: Color, angle: f32, state: &GameState,
 	)
 	{
-		let w = self.desc.width as f32;
-		let h = self.desc.height as f32;
+		self.draw_ex(pos, variant, tint, angle, Flag::zero(), state);
+	}
+
+	/// Like `draw_rotated`, but also takes `FLIP_HORIZONTAL`/`FLIP_VERTICAL` flags. Flipping
+	/// mirrors the sprite about its center.
+	pub fn draw_ex(
+		&self, pos: Point2<f32>, variant: i32, tint: Color, angle: f32, flags: BitmapDrawingFlags,
+		state: &GameState,
+	)
+	{
 		let atlas_bmp = &self.variants[variant as usize];
+		let pivot = self.pivot(variant);
+
+		let mut cx = pivot.x;
+		let mut cy = pivot.y;
+		// Allegro flips within the region, so mirror the pivot to match.
+		if flags & FLIP_HORIZONTAL
+		{
+			cx = atlas_bmp.width() - cx;
+		}
+		if flags & FLIP_VERTICAL
+		{
+			cy = atlas_bmp.height() - cy;
+		}
 
 		state.core.draw_tinted_scaled_rotated_bitmap_region(
 			&state.atlas.pages[atlas_bmp.page].bitmap,
 			atlas_bmp.start.x,
 			atlas_bmp.start.y,
-			w,
-			h,
+			atlas_bmp.width(),
+			atlas_bmp.height(),
 			tint,
-			self.desc.center_x as f32 + w / 2.,
-			self.desc.center_y as f32 + h / 2.,
+			cx,
+			cy,
 			pos.x,
 			pos.y,
 			1.,
 			1.,
 			angle,
-			Flag::zero(),
+			flags,
 		);
 	}
 }
+
+#[test]
+fn test_trim_rect()
+{
+	// 8x8 frame with opaque pixels in the 3x3 square at (2, 3).
+	let rect = trim_rect(8, 8, |x, y| x >= 2 && x < 5 && y >= 3 && y < 6);
+	assert_eq!(rect, Some((2, 3, 3, 3)));
+
+	let rect = trim_rect(8, 8, |_, _| true);
+	assert_eq!(rect, Some((0, 0, 8, 8)));
+
+	let rect = trim_rect(8, 8, |_, _| false);
+	assert_eq!(rect, None);
+}
+
+#[test]
+fn test_trimmed_frame()
+{
+	// 8x8 frame with opaque pixels in the 3x3 square at (2, 3), pivoting 1 pixel right and up
+	// of the frame's center.
+	let mut desc = SpriteDesc {
+		bitmap: "test.png".to_string(),
+		width: 8,
+		height: 8,
+		frame_rate: 1.,
+		center_x: 1,
+		center_y: -1,
+		trim: true,
+		playback: Playback::Loop,
+	};
+	let is_opaque = |x, y| x >= 2 && x < 5 && y >= 3 && y < 6;
+	let (dx, dy, w, h) = frame_rect(&desc, is_opaque);
+	assert_eq!((dx, dy, w, h), (2, 3, 3, 3));
+
+	let sprite = Sprite {
+		desc: desc.clone(),
+		variants: vec![atlas::AtlasBitmap {
+			start: Point2::new(10., 10.),
+			end: Point2::new(10. + w as f32, 10. + h as f32),
+			page: 0,
+		}],
+		offsets: vec![Vector2::new(dx as f32, dy as f32)],
+	};
+	// The pivot moves by the trimmed offset, so the opaque pixels land where they would have
+	// without trimming.
+	assert_eq!(sprite.pivot(0), Vector2::new(3., 0.));
+	assert_eq!(sprite.pivot(0) + sprite.offsets[0], Vector2::new(5., 3.));
+
+	desc.trim = false;
+	assert_eq!(frame_rect(&desc, is_opaque), (0, 0, 8, 8));
+	// Fully transparent frames are kept whole.
+	desc.trim = true;
+	assert_eq!(frame_rect(&desc, |_, _| false), (0, 0, 8, 8));
+}
+
+#[test]
+fn test_frame_uv_rect()
+{
+	let sprite = Sprite {
+		desc: SpriteDesc {
+			bitmap: "test.png".to_string(),
+			width: 32,
+			height: 64,
+			frame_rate: 1.,
+			center_x: 0,
+			center_y: 0,
+			trim: false,
+			playback: Playback::Loop,
+		},
+		variants: vec![
+			atlas::AtlasBitmap {
+				start: Point2::new(1., 1.),
+				end: Point2::new(33., 65.),
+				page: 0,
+			},
+			atlas::AtlasBitmap {
+				start: Point2::new(64., 128.),
+				end: Point2::new(96., 192.),
+				page: 2,
+			},
+		],
+		offsets: vec![Vector2::zeros(), Vector2::new(3., 4.)],
+	};
+	let atlas = atlas::Atlas::new(256);
+
+	let (page, uv, offset) = sprite.frame_uv_rect(1, &atlas);
+	assert_eq!(page, 2);
+	assert_eq!(uv, [0.25, 0.5, 0.375, 0.75]);
+	assert_eq!(offset, Vector2::new(3., 4.));
+	assert_eq!(sprite.frame_uv_rect(0, &atlas).2, Vector2::zeros());
+}
+
+#[test]
+fn test_playback_frame()
+{
+	let variants = |playback| {
+		(0..8)
+			.map(|f| playback_frame(f, 3, playback).0)
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(variants(Playback::Loop), vec![0, 1, 2, 0, 1, 2, 0, 1]);
+	assert_eq!(variants(Playback::Once), vec![0, 1, 2, 2, 2, 2, 2, 2]);
+	assert_eq!(variants(Playback::PingPong), vec![0, 1, 2, 1, 0, 1, 2, 1]);
+
+	assert_eq!(playback_frame(7, 3, Playback::Loop).1, 2);
+	assert_eq!(playback_frame(7, 3, Playback::Once).1, 1);
+	assert_eq!(playback_frame(7, 3, Playback::PingPong).1, 1);
+	assert_eq!(playback_frame(5, 1, Playback::PingPong), (0, 5));
+}
+
+#[test]
+fn test_playback_progress()
+{
+	assert_eq!(playback_progress(1.5, 1, Playback::Loop), 0.);
+	assert_eq!(playback_progress(0., 0, Playback::Loop), 0.);
+	assert_eq!(playback_progress(1., 4, Playback::Loop), 0.25);
+	assert_eq!(playback_progress(5., 4, Playback::Loop), 0.25);
+	assert_eq!(playback_progress(6., 4, Playback::Once), 1.);
+	assert_eq!(playback_progress(3., 3, Playback::PingPong), 0.75);
+}
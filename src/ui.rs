@@ -15,6 +15,12 @@ pub const VERT_SPACE: f32 = 16.;
 pub const BUTTON_WIDTH: f32 = 128.;
 pub const BUTTON_HEIGHT: f32 = 16.;
 pub const CONTROL_WIDTH: f32 = 80.;
+/// How long a navigation key must be held before it starts repeating, in seconds.
+pub const NAV_REPEAT_DELAY: f64 = 0.4;
+/// Time between navigation repeats, in seconds.
+pub const NAV_REPEAT_INTERVAL: f64 = 0.1;
+/// Fraction of the handle travel a log slider moves per keyboard step.
+pub const LOG_SLIDER_STEP: f32 = 0.05;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Action
@@ -25,14 +31,131 @@ pub enum Action
 	Quit,
 	Back,
 	Forward(fn(&mut game_state::GameState) -> Result<SubScreen>),
-	ToggleFullscreen,
-	ToggleFracScale,
+	Fullscreen(bool),
+	FracScale(bool),
 	ChangeInput(controls::Action, usize),
 	MouseSensitivity(f32),
 	UiScale(f32),
 	MusicVolume(f32),
 	SfxVolume(f32),
 	CameraSpeed(i32),
+	/// Contents of the seed field, empty for a random seed.
+	Seed(String),
+}
+
+/// Widget state that `WidgetBuilder` can configure.
+trait BuildableWidget
+{
+	fn size_mut(&mut self) -> &mut Vector2<f32>;
+	fn tooltip_mut(&mut self) -> &mut Option<String>;
+
+	/// Called by `WidgetBuilder::build` once all the options are set.
+	fn finish(&mut self) {}
+}
+
+/// Implements `BuildableWidget` for widgets with `size` and `tooltip` fields.
+macro_rules! buildable_widget {
+	($($widget:ty),*) => {
+		$(
+			impl BuildableWidget for $widget
+			{
+				fn size_mut(&mut self) -> &mut Vector2<f32>
+				{
+					&mut self.size
+				}
+
+				fn tooltip_mut(&mut self) -> &mut Option<String>
+				{
+					&mut self.tooltip
+				}
+			}
+		)*
+	};
+}
+
+buildable_widget!(Button, Toggle, Slider, Checkbox, Label);
+
+impl BuildableWidget for TextField
+{
+	fn size_mut(&mut self) -> &mut Vector2<f32>
+	{
+		&mut self.size
+	}
+
+	fn tooltip_mut(&mut self) -> &mut Option<String>
+	{
+		&mut self.tooltip
+	}
+
+	fn finish(&mut self)
+	{
+		self.text = self.text.chars().take(self.max_len).collect();
+		self.cursor = self.text.chars().count();
+	}
+}
+
+/// Configures a widget created by one of the `builder` functions.
+struct WidgetBuilder<T>
+{
+	widget: T,
+}
+
+impl<T: BuildableWidget> WidgetBuilder<T>
+{
+	fn new(widget: T) -> Self
+	{
+		Self { widget: widget }
+	}
+
+	fn width(mut self, width: f32) -> Self
+	{
+		self.widget.size_mut().x = width;
+		self
+	}
+
+	fn height(mut self, height: f32) -> Self
+	{
+		self.widget.size_mut().y = height;
+		self
+	}
+
+	fn tooltip(mut self, tooltip: &str) -> Self
+	{
+		*self.widget.tooltip_mut() = Some(tooltip.into());
+		self
+	}
+
+	fn build(mut self) -> T
+	{
+		self.widget.finish();
+		self.widget
+	}
+}
+
+impl WidgetBuilder<Slider>
+{
+	fn round_to(mut self, round_to: f32) -> Self
+	{
+		self.widget.round_to = round_to;
+		self
+	}
+
+	/// See `Slider::new_log`.
+	fn log_scale(mut self) -> Self
+	{
+		assert!(self.widget.min_pos > 0. && self.widget.max_pos > self.widget.min_pos);
+		self.widget.log_scale = true;
+		self
+	}
+}
+
+impl WidgetBuilder<TextField>
+{
+	fn max_len(mut self, max_len: usize) -> Self
+	{
+		self.widget.max_len = max_len;
+		self
+	}
 }
 
 #[derive(Clone)]
@@ -41,20 +164,40 @@ struct Button
 	loc: Point2<f32>,
 	size: Vector2<f32>,
 	text: String,
+	icon: Option<String>,
 	action: Action,
 	selected: bool,
+	tooltip: Option<String>,
 }
 
 impl Button
 {
 	fn new(w: f32, h: f32, text: &str, action: Action) -> Self
 	{
-		Self {
+		Self::builder(text, action).width(w).height(h).build()
+	}
+
+	/// Starts building a button with the default size.
+	fn builder(text: &str, action: Action) -> WidgetBuilder<Self>
+	{
+		WidgetBuilder::new(Self {
 			loc: Point2::new(0., 0.),
-			size: Vector2::new(w, h),
+			size: Vector2::new(BUTTON_WIDTH, BUTTON_HEIGHT),
 			text: text.into(),
+			icon: None,
 			action: action,
 			selected: false,
+			tooltip: None,
+		})
+	}
+
+	/// A button that draws a bitmap instead of text. The bitmap should be cached in the
+	/// `GameState` before the button is drawn, otherwise `text` is drawn instead.
+	fn new_icon(w: f32, h: f32, bitmap_name: &str, text: &str, action: Action) -> Self
+	{
+		Self {
+			icon: Some(bitmap_name.into()),
+			..Self::new(w, h, text, action)
 		}
 	}
 
@@ -72,14 +215,36 @@ impl Button
 	{
 		let c_ui = if self.selected { SELECTED } else { UNSELECTED };
 
-		state.core.draw_text(
-			state.ui_font(),
-			c_ui,
-			self.loc.x.round(),
-			(self.loc.y - state.ui_font().get_line_height() as f32 / 2.).round(),
-			FontAlign::Centre,
-			&self.text,
-		);
+		if let Some(Ok(bitmap)) = self.icon.as_ref().map(|icon| state.get_bitmap(icon))
+		{
+			let s = state.options.ui_scale;
+			let bw = bitmap.get_width() as f32;
+			let bh = bitmap.get_height() as f32;
+			state.core.draw_tinted_scaled_bitmap(
+				bitmap,
+				c_ui,
+				0.,
+				0.,
+				bw,
+				bh,
+				(self.loc.x - s * bw / 2.).round(),
+				(self.loc.y - s * bh / 2.).round(),
+				s * bw,
+				s * bh,
+				Flag::zero(),
+			);
+		}
+		else
+		{
+			state.core.draw_text(
+				state.ui_font(),
+				c_ui,
+				self.loc.x.round(),
+				(self.loc.y - state.ui_font().get_line_height() as f32 / 2.).round(),
+				FontAlign::Centre,
+				&self.text,
+			);
+		}
 	}
 
 	fn input(&mut self, state: &mut game_state::GameState, event: &Event) -> Option<Action>
@@ -120,16 +285,31 @@ impl Button
 			Event::MouseButtonUp { x, y, .. } =>
 			{
 				let (x, y) = state.transform_mouse(*x as f32, *y as f32);
-				if x > start.x && x < end.x && y > start.y && y < end.y
+				if let Some(action) = self.click(s, x, y)
 				{
 					state.sfx.play_sound("data/ui2.ogg").unwrap();
-					return Some(self.action.clone());
+					return Some(action);
 				}
 			}
 			_ => (),
 		}
 		None
 	}
+
+	/// The action for a click at `x`, `y` (in buffer coordinates), if it hits the button.
+	fn click(&self, s: f32, x: f32, y: f32) -> Option<Action>
+	{
+		let start = self.loc - s * self.size / 2.;
+		let end = self.loc + s * self.size / 2.;
+		if x > start.x && x < end.x && y > start.y && y < end.y
+		{
+			Some(self.action.clone())
+		}
+		else
+		{
+			None
+		}
+	}
 }
 
 #[derive(Clone)]
@@ -141,6 +321,7 @@ struct Toggle
 	cur_value: usize,
 	action_fn: fn(usize) -> Action,
 	selected: bool,
+	tooltip: Option<String>,
 }
 
 impl Toggle
@@ -149,14 +330,26 @@ impl Toggle
 		w: f32, h: f32, cur_value: usize, texts: Vec<String>, action_fn: fn(usize) -> Action,
 	) -> Self
 	{
-		Self {
+		Self::builder(cur_value, texts, action_fn)
+			.width(w)
+			.height(h)
+			.build()
+	}
+
+	/// Starts building a toggle with the default size.
+	fn builder(
+		cur_value: usize, texts: Vec<String>, action_fn: fn(usize) -> Action,
+	) -> WidgetBuilder<Self>
+	{
+		WidgetBuilder::new(Self {
 			loc: Point2::new(0., 0.),
-			size: Vector2::new(w, h),
+			size: Vector2::new(BUTTON_WIDTH, BUTTON_HEIGHT),
 			texts: texts,
 			cur_value: cur_value,
 			action_fn: action_fn,
 			selected: false,
-		}
+			tooltip: None,
+		})
 	}
 
 	fn width(&self) -> f32
@@ -230,6 +423,15 @@ impl Toggle
 	}
 }
 
+/// Produces the action for a slider value.
+#[derive(Clone)]
+enum SliderFn
+{
+	Float(fn(f32) -> Action),
+	/// The value is rounded to an integer before being passed on.
+	Int(fn(i32) -> Action),
+}
+
 #[derive(Clone)]
 struct Slider
 {
@@ -240,8 +442,10 @@ struct Slider
 	max_pos: f32,
 	grabbed: bool,
 	selected: bool,
+	tooltip: Option<String>,
 	round_to: f32,
-	action_fn: fn(f32) -> Action,
+	log_scale: bool,
+	action_fn: SliderFn,
 }
 
 impl Slider
@@ -251,19 +455,82 @@ impl Slider
 		action_fn: fn(f32) -> Action,
 	) -> Self
 	{
-		Self {
+		Self::builder(cur_pos, min_pos, max_pos, action_fn)
+			.width(w)
+			.height(h)
+			.round_to(round_to)
+			.build()
+	}
+
+	/// Starts building a slider with the default size and a step of 0.1.
+	fn builder(
+		cur_pos: f32, min_pos: f32, max_pos: f32, action_fn: fn(f32) -> Action,
+	) -> WidgetBuilder<Self>
+	{
+		Self::builder_with_action_fn(cur_pos, min_pos, max_pos, SliderFn::Float(action_fn))
+	}
+
+	fn builder_with_action_fn(
+		cur_pos: f32, min_pos: f32, max_pos: f32, action_fn: SliderFn,
+	) -> WidgetBuilder<Self>
+	{
+		WidgetBuilder::new(Self {
 			loc: Point2::new(0., 0.),
-			size: Vector2::new(w, h),
+			size: Vector2::new(BUTTON_WIDTH, BUTTON_HEIGHT),
 			cur_pos: cur_pos,
 			min_pos: min_pos,
 			max_pos: max_pos,
 			grabbed: false,
 			selected: false,
-			round_to: round_to,
+			tooltip: None,
+			round_to: 0.1,
+			log_scale: false,
 			action_fn: action_fn,
+		})
+	}
+
+	/// A slider over integer values. The action receives the already rounded value.
+	fn new_int(
+		w: f32, h: f32, cur_pos: i32, min_pos: i32, max_pos: i32, step: i32,
+		action_fn: fn(i32) -> Action,
+	) -> Self
+	{
+		Self::builder_with_action_fn(
+			cur_pos as f32,
+			min_pos as f32,
+			max_pos as f32,
+			SliderFn::Int(action_fn),
+		)
+		.width(w)
+		.height(h)
+		.round_to(step as f32)
+		.build()
+	}
+
+	fn action(&self) -> Action
+	{
+		match self.action_fn
+		{
+			SliderFn::Float(f) => f(self.cur_pos),
+			SliderFn::Int(f) => f(self.cur_pos.round() as i32),
 		}
 	}
 
+	/// Like `new`, but the handle position maps logarithmically onto the value range. Both
+	/// `min_pos` and `max_pos` must be positive.
+	fn new_log(
+		w: f32, h: f32, cur_pos: f32, min_pos: f32, max_pos: f32, round_to: f32,
+		action_fn: fn(f32) -> Action,
+	) -> Self
+	{
+		Self::builder(cur_pos, min_pos, max_pos, action_fn)
+			.width(w)
+			.height(h)
+			.round_to(round_to)
+			.log_scale()
+			.build()
+	}
+
 	fn width(&self) -> f32
 	{
 		self.size.x
@@ -277,6 +544,63 @@ impl Slider
 	fn round_cur_pos(&mut self)
 	{
 		self.cur_pos = (self.cur_pos / self.round_to).round() * self.round_to;
+		self.cur_pos = utils::clamp(self.cur_pos, self.min_pos, self.max_pos);
+	}
+
+	/// Converts a value to the handle position, in [0, 1].
+	fn value_to_frac(&self, value: f32) -> f32
+	{
+		if self.log_scale
+		{
+			(value / self.min_pos).ln() / (self.max_pos / self.min_pos).ln()
+		}
+		else
+		{
+			utils::remap(value, self.min_pos, self.max_pos, 0., 1.)
+		}
+	}
+
+	/// Converts a handle position, in [0, 1], to a value.
+	fn frac_to_value(&self, frac: f32) -> f32
+	{
+		if self.log_scale
+		{
+			self.min_pos * (self.max_pos / self.min_pos).powf(frac)
+		}
+		else
+		{
+			utils::remap(frac, 0., 1., self.min_pos, self.max_pos)
+		}
+	}
+
+	fn step(&mut self, dir: f32)
+	{
+		if self.log_scale
+		{
+			self.round_cur_pos();
+			let old_pos = self.cur_pos;
+			let frac = self.value_to_frac(self.cur_pos) + dir * LOG_SLIDER_STEP;
+			self.cur_pos = self.frac_to_value(utils::clamp(frac, 0., 1.));
+			self.round_cur_pos();
+			// Rounding can undo a small step, so always move by at least `round_to`.
+			if dir > 0. && self.cur_pos <= old_pos
+			{
+				self.cur_pos = utils::min(old_pos + self.round_to, self.max_pos);
+			}
+			else if dir < 0. && self.cur_pos >= old_pos
+			{
+				self.cur_pos = utils::max(old_pos - self.round_to, self.min_pos);
+			}
+		}
+		else
+		{
+			self.cur_pos = utils::clamp(
+				self.cur_pos + dir * self.round_to,
+				self.min_pos,
+				self.max_pos,
+			);
+			self.round_cur_pos();
+		}
 	}
 
 	fn draw(&self, state: &game_state::GameState)
@@ -285,8 +609,7 @@ impl Slider
 		let c_ui = if self.selected { SELECTED } else { UNSELECTED };
 
 		let w = s * self.width();
-		let cursor_x =
-			self.loc.x - w / 2. + w * (self.cur_pos - self.min_pos) / (self.max_pos - self.min_pos);
+		let cursor_x = self.loc.x - w / 2. + w * self.value_to_frac(self.cur_pos);
 		let start_x = self.loc.x - w / 2.;
 		let end_x = self.loc.x + w / 2.;
 
@@ -305,7 +628,11 @@ impl Slider
 		}
 		//state.prim.draw_filled_circle(self.loc.x - w / 2. + w * self.cur_pos / self.max_pos, self.loc.y, 8., c_ui);
 
-		let text = format!("{:.2}", self.cur_pos);
+		let text = match self.action_fn
+		{
+			SliderFn::Float(_) => format!("{:.2}", self.cur_pos),
+			SliderFn::Int(_) => format!("{}", self.cur_pos.round() as i32),
+		};
 		let text = if text.contains('.')
 		{
 			text.trim_end_matches("0").trim_end_matches(".")
@@ -332,17 +659,27 @@ impl Slider
 		let end = self.loc + s * self.size / 2.;
 		match event
 		{
-			Event::MouseAxes { x, y, .. } =>
+			Event::MouseAxes { x, y, dz, .. } =>
 			{
 				let (x, y) = state.transform_mouse(*x as f32, *y as f32);
 				if x > start.x && x < end.x && y > start.y && y < end.y
 				{
 					if self.grabbed
 					{
-						self.cur_pos = self.min_pos
-							+ (x - start.x) / (s * self.width()) * (self.max_pos - self.min_pos);
+						self.cur_pos = self.frac_to_value((x - start.x) / (s * self.width()));
 						self.round_cur_pos();
-						return Some((self.action_fn)(self.cur_pos));
+						return Some(self.action());
+					}
+					else if *dz != 0
+					{
+						let old_pos = self.cur_pos;
+						self.step(*dz as f32);
+						if self.cur_pos != old_pos
+						{
+							state.sfx.play_sound("data/ui2.ogg").unwrap();
+							return Some(self.action());
+						}
+						return Some(Action::SelectMe);
 					}
 					else
 					{
@@ -361,15 +698,13 @@ impl Slider
 				{
 					state.sfx.play_sound("data/ui2.ogg").unwrap();
 					self.grabbed = true;
-					self.cur_pos = self.min_pos
-						+ (x - start.x) / (s * self.width()) * (self.max_pos - self.min_pos);
+					self.cur_pos = self.frac_to_value((x - start.x) / (s * self.width()));
 					self.round_cur_pos();
-					return Some((self.action_fn)(self.cur_pos));
+					return Some(self.action());
 				}
 			}
 			Event::KeyDown { keycode, .. } =>
 			{
-				let increment = self.round_to;
 				if self.selected
 				{
 					match keycode
@@ -379,9 +714,8 @@ impl Slider
 							if self.cur_pos > self.min_pos
 							{
 								state.sfx.play_sound("data/ui2.ogg").unwrap();
-								self.cur_pos = utils::max(self.min_pos, self.cur_pos - increment);
-								self.round_cur_pos();
-								return Some((self.action_fn)(self.cur_pos));
+								self.step(-1.);
+								return Some(self.action());
 							}
 						}
 						KeyCode::Right =>
@@ -389,9 +723,8 @@ impl Slider
 							if self.cur_pos < self.max_pos
 							{
 								state.sfx.play_sound("data/ui2.ogg").unwrap();
-								self.cur_pos = utils::min(self.max_pos, self.cur_pos + increment);
-								self.round_cur_pos();
-								return Some((self.action_fn)(self.cur_pos));
+								self.step(1.);
+								return Some(self.action());
 							}
 						}
 						_ => (),
@@ -404,23 +737,139 @@ impl Slider
 	}
 }
 
+#[derive(Clone)]
+struct Checkbox
+{
+	loc: Point2<f32>,
+	size: Vector2<f32>,
+	text: String,
+	checked: bool,
+	action_fn: fn(bool) -> Action,
+	selected: bool,
+	tooltip: Option<String>,
+}
+
+impl Checkbox
+{
+	fn new(w: f32, h: f32, text: &str, checked: bool, action_fn: fn(bool) -> Action) -> Self
+	{
+		Self::builder(text, checked, action_fn)
+			.width(w)
+			.height(h)
+			.build()
+	}
+
+	/// Starts building a checkbox with the default size.
+	fn builder(text: &str, checked: bool, action_fn: fn(bool) -> Action) -> WidgetBuilder<Self>
+	{
+		WidgetBuilder::new(Self {
+			loc: Point2::new(0., 0.),
+			size: Vector2::new(BUTTON_WIDTH, BUTTON_HEIGHT),
+			text: text.into(),
+			checked: checked,
+			action_fn: action_fn,
+			selected: false,
+			tooltip: None,
+		})
+	}
+
+	fn width(&self) -> f32
+	{
+		self.size.x
+	}
+
+	fn height(&self) -> f32
+	{
+		self.size.y
+	}
+
+	fn draw(&self, state: &game_state::GameState)
+	{
+		let c_ui = if self.selected { SELECTED } else { UNSELECTED };
+
+		state.core.draw_text(
+			state.ui_font(),
+			c_ui,
+			self.loc.x.round(),
+			(self.loc.y - state.ui_font().get_line_height() as f32 / 2.).round(),
+			FontAlign::Centre,
+			&format!("[{}] {}", if self.checked { "x" } else { " " }, self.text),
+		);
+	}
+
+	fn input(&mut self, state: &mut game_state::GameState, event: &Event) -> Option<Action>
+	{
+		let s = state.options.ui_scale;
+		let start = self.loc - s * self.size / 2.;
+		let end = self.loc + s * self.size / 2.;
+		match event
+		{
+			Event::MouseAxes { x, y, .. } =>
+			{
+				let (x, y) = state.transform_mouse(*x as f32, *y as f32);
+				if x > start.x && x < end.x && y > start.y && y < end.y
+				{
+					return Some(Action::SelectMe);
+				}
+			}
+			Event::KeyDown { keycode, .. } => match keycode
+			{
+				KeyCode::Enter | KeyCode::Space | KeyCode::Left | KeyCode::Right =>
+				{
+					if self.selected
+					{
+						state.sfx.play_sound("data/ui2.ogg").unwrap();
+						return Some(self.toggle());
+					}
+				}
+				_ => (),
+			},
+			Event::MouseButtonUp { x, y, .. } =>
+			{
+				let (x, y) = state.transform_mouse(*x as f32, *y as f32);
+				if x > start.x && x < end.x && y > start.y && y < end.y
+				{
+					state.sfx.play_sound("data/ui2.ogg").unwrap();
+					return Some(self.toggle());
+				}
+			}
+			_ => (),
+		}
+		None
+	}
+
+	fn toggle(&mut self) -> Action
+	{
+		self.checked = !self.checked;
+		(self.action_fn)(self.checked)
+	}
+}
+
 #[derive(Clone)]
 struct Label
 {
 	loc: Point2<f32>,
 	size: Vector2<f32>,
 	text: String,
+	tooltip: Option<String>,
 }
 
 impl Label
 {
 	fn new(w: f32, h: f32, text: &str) -> Self
 	{
-		Self {
+		Self::builder(text).width(w).height(h).build()
+	}
+
+	/// Starts building a label with the default size.
+	fn builder(text: &str) -> WidgetBuilder<Self>
+	{
+		WidgetBuilder::new(Self {
 			loc: Point2::new(0., 0.),
-			size: Vector2::new(w, h),
+			size: Vector2::new(BUTTON_WIDTH, BUTTON_HEIGHT),
 			text: text.into(),
-		}
+			tooltip: None,
+		})
 	}
 
 	fn width(&self) -> f32
@@ -445,8 +894,220 @@ impl Label
 		);
 	}
 
-	fn input(&mut self, _state: &mut game_state::GameState, _event: &Event) -> Option<Action>
+	fn input(&mut self, _state: &mut game_state::GameState, _event: &Event) -> Option<Action>
+	{
+		None
+	}
+}
+
+#[derive(Clone)]
+struct TextField
+{
+	loc: Point2<f32>,
+	size: Vector2<f32>,
+	text: String,
+	/// Cursor position, in characters.
+	cursor: usize,
+	max_len: usize,
+	/// Whether the text changed since the last time `action_fn` was called.
+	dirty: bool,
+	action_fn: fn(String) -> Action,
+	selected: bool,
+	tooltip: Option<String>,
+}
+
+impl TextField
+{
+	fn new(w: f32, h: f32, text: &str, max_len: usize, action_fn: fn(String) -> Action) -> Self
+	{
+		Self::builder(text, action_fn)
+			.width(w)
+			.height(h)
+			.max_len(max_len)
+			.build()
+	}
+
+	/// Starts building a text field with the default size and a maximum length of 32.
+	fn builder(text: &str, action_fn: fn(String) -> Action) -> WidgetBuilder<Self>
+	{
+		WidgetBuilder::new(Self {
+			loc: Point2::new(0., 0.),
+			size: Vector2::new(BUTTON_WIDTH, BUTTON_HEIGHT),
+			cursor: 0,
+			text: text.into(),
+			max_len: 32,
+			dirty: false,
+			action_fn: action_fn,
+			selected: false,
+			tooltip: None,
+		})
+	}
+
+	fn width(&self) -> f32
+	{
+		self.size.x
+	}
+
+	fn height(&self) -> f32
+	{
+		self.size.y
+	}
+
+	fn byte_offset(&self, cursor: usize) -> usize
+	{
+		self.text
+			.char_indices()
+			.nth(cursor)
+			.map(|(i, _)| i)
+			.unwrap_or(self.text.len())
+	}
+
+	fn insert_char(&mut self, c: char)
+	{
+		if c.is_control() || self.text.chars().count() >= self.max_len
+		{
+			return;
+		}
+		let offset = self.byte_offset(self.cursor);
+		self.text.insert(offset, c);
+		self.cursor += 1;
+		self.dirty = true;
+	}
+
+	fn backspace(&mut self)
+	{
+		if self.cursor > 0
+		{
+			self.cursor -= 1;
+			let offset = self.byte_offset(self.cursor);
+			self.text.remove(offset);
+			self.dirty = true;
+		}
+	}
+
+	fn delete(&mut self)
+	{
+		if self.cursor < self.text.chars().count()
+		{
+			let offset = self.byte_offset(self.cursor);
+			self.text.remove(offset);
+			self.dirty = true;
+		}
+	}
+
+	/// Returns the action for the current text if it changed since the last commit.
+	fn commit(&mut self) -> Option<Action>
+	{
+		if self.dirty
+		{
+			self.dirty = false;
+			Some((self.action_fn)(self.text.clone()))
+		}
+		else
+		{
+			None
+		}
+	}
+
+	/// Keys that the field uses for editing, and so shouldn't navigate the widget list.
+	fn captures_key(&self, keycode: KeyCode) -> bool
+	{
+		match keycode
+		{
+			KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => true,
+			_ => false,
+		}
+	}
+
+	fn draw(&self, state: &game_state::GameState)
+	{
+		let s = state.options.ui_scale;
+		let c_ui = if self.selected { SELECTED } else { UNSELECTED };
+		let font = state.ui_font();
+		let lh = font.get_line_height() as f32;
+
+		let text_w = font.get_text_width(&self.text) as f32;
+		let start_x = (self.loc.x - text_w / 2.).round();
+		state.core.draw_text(
+			font,
+			c_ui,
+			start_x,
+			(self.loc.y - lh / 2.).round(),
+			FontAlign::Left,
+			&self.text,
+		);
+
+		let w = s * self.width();
+		state.prim.draw_line(
+			self.loc.x - w / 2.,
+			self.loc.y + lh / 2.,
+			self.loc.x + w / 2.,
+			self.loc.y + lh / 2.,
+			c_ui,
+			s,
+		);
+
+		if self.selected && (state.core.get_time() * 2.) as i64 % 2 == 0
+		{
+			let caret_x =
+				start_x + font.get_text_width(&self.text[..self.byte_offset(self.cursor)]) as f32;
+			state.prim.draw_line(
+				caret_x,
+				self.loc.y - lh / 2.,
+				caret_x,
+				self.loc.y + lh / 2.,
+				c_ui,
+				s,
+			);
+		}
+	}
+
+	fn input(&mut self, state: &mut game_state::GameState, event: &Event) -> Option<Action>
 	{
+		let s = state.options.ui_scale;
+		let start = self.loc - s * self.size / 2.;
+		let end = self.loc + s * self.size / 2.;
+		match event
+		{
+			Event::MouseAxes { x, y, .. } =>
+			{
+				let (x, y) = state.transform_mouse(*x as f32, *y as f32);
+				if x > start.x && x < end.x && y > start.y && y < end.y
+				{
+					return Some(Action::SelectMe);
+				}
+			}
+			Event::KeyChar {
+				keycode, unichar, ..
+			} =>
+			{
+				if self.selected
+				{
+					match keycode
+					{
+						KeyCode::Enter | KeyCode::PadEnter =>
+						{
+							if let Some(action) = self.commit()
+							{
+								state.sfx.play_sound("data/ui2.ogg").unwrap();
+								return Some(action);
+							}
+						}
+						KeyCode::Backspace => self.backspace(),
+						KeyCode::Delete => self.delete(),
+						KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+						KeyCode::Right =>
+						{
+							self.cursor = utils::min(self.cursor + 1, self.text.chars().count())
+						}
+						KeyCode::Home => self.cursor = 0,
+						KeyCode::End => self.cursor = self.text.chars().count(),
+						_ => self.insert_char(*unichar),
+					}
+				}
+			}
+			_ => (),
+		}
 		None
 	}
 }
@@ -455,8 +1116,10 @@ impl Label
 enum Widget
 {
 	Button(Button),
+	Checkbox(Checkbox),
 	Label(Label),
 	Slider(Slider),
+	TextField(TextField),
 	Toggle(Toggle),
 }
 
@@ -467,8 +1130,10 @@ impl Widget
 		match self
 		{
 			Widget::Button(w) => w.height(),
+			Widget::Checkbox(w) => w.height(),
 			Widget::Label(w) => w.height(),
 			Widget::Slider(w) => w.height(),
+			Widget::TextField(w) => w.height(),
 			Widget::Toggle(w) => w.height(),
 		}
 	}
@@ -478,8 +1143,10 @@ impl Widget
 		match self
 		{
 			Widget::Button(w) => w.width(),
+			Widget::Checkbox(w) => w.width(),
 			Widget::Label(w) => w.width(),
 			Widget::Slider(w) => w.width(),
+			Widget::TextField(w) => w.width(),
 			Widget::Toggle(w) => w.width(),
 		}
 	}
@@ -489,19 +1156,36 @@ impl Widget
 		match self
 		{
 			Widget::Button(w) => w.loc,
+			Widget::Checkbox(w) => w.loc,
 			Widget::Label(w) => w.loc,
 			Widget::Slider(w) => w.loc,
+			Widget::TextField(w) => w.loc,
 			Widget::Toggle(w) => w.loc,
 		}
 	}
 
+	fn tooltip(&self) -> Option<&str>
+	{
+		match self
+		{
+			Widget::Button(w) => w.tooltip.as_deref(),
+			Widget::Checkbox(w) => w.tooltip.as_deref(),
+			Widget::Label(w) => w.tooltip.as_deref(),
+			Widget::Slider(w) => w.tooltip.as_deref(),
+			Widget::TextField(w) => w.tooltip.as_deref(),
+			Widget::Toggle(w) => w.tooltip.as_deref(),
+		}
+	}
+
 	fn selectable(&self) -> bool
 	{
 		match self
 		{
 			Widget::Button(_) => true,
+			Widget::Checkbox(_) => true,
 			Widget::Label(_) => false,
 			Widget::Slider(_) => true,
+			Widget::TextField(_) => true,
 			Widget::Toggle(_) => true,
 		}
 	}
@@ -511,8 +1195,10 @@ impl Widget
 		match self
 		{
 			Widget::Button(ref mut w) => w.loc = loc,
+			Widget::Checkbox(ref mut w) => w.loc = loc,
 			Widget::Label(ref mut w) => w.loc = loc,
 			Widget::Slider(ref mut w) => w.loc = loc,
+			Widget::TextField(ref mut w) => w.loc = loc,
 			Widget::Toggle(ref mut w) => w.loc = loc,
 		}
 	}
@@ -522,8 +1208,10 @@ impl Widget
 		match self
 		{
 			Widget::Button(w) => w.selected,
+			Widget::Checkbox(w) => w.selected,
 			Widget::Label(_) => false,
 			Widget::Slider(w) => w.selected,
+			Widget::TextField(w) => w.selected,
 			Widget::Toggle(w) => w.selected,
 		}
 	}
@@ -533,8 +1221,10 @@ impl Widget
 		match self
 		{
 			Widget::Button(ref mut w) => w.selected = selected,
+			Widget::Checkbox(ref mut w) => w.selected = selected,
 			Widget::Label(_) => (),
 			Widget::Slider(ref mut w) => w.selected = selected,
+			Widget::TextField(ref mut w) => w.selected = selected,
 			Widget::Toggle(ref mut w) => w.selected = selected,
 		}
 	}
@@ -544,19 +1234,42 @@ impl Widget
 		match self
 		{
 			Widget::Button(w) => w.draw(state),
+			Widget::Checkbox(w) => w.draw(state),
 			Widget::Label(w) => w.draw(state),
 			Widget::Slider(w) => w.draw(state),
+			Widget::TextField(w) => w.draw(state),
 			Widget::Toggle(w) => w.draw(state),
 		}
 	}
 
+	fn captures_key(&self, keycode: KeyCode) -> bool
+	{
+		match self
+		{
+			Widget::TextField(w) => w.captures_key(keycode),
+			_ => false,
+		}
+	}
+
+	/// Called when the widget loses the selection.
+	fn deselect(&mut self) -> Option<Action>
+	{
+		match self
+		{
+			Widget::TextField(w) => w.commit(),
+			_ => None,
+		}
+	}
+
 	fn input(&mut self, state: &mut game_state::GameState, event: &Event) -> Option<Action>
 	{
 		match self
 		{
 			Widget::Button(w) => w.input(state, event),
+			Widget::Checkbox(w) => w.input(state, event),
 			Widget::Label(w) => w.input(state, event),
 			Widget::Slider(w) => w.input(state, event),
+			Widget::TextField(w) => w.input(state, event),
 			Widget::Toggle(w) => w.input(state, event),
 		}
 	}
@@ -567,6 +1280,31 @@ struct WidgetList
 	widgets: Vec<Vec<Widget>>,
 	cur_selection: (usize, usize),
 	pos: Point2<f32>,
+	/// If set, only this much height around `pos` is visible and the rest of the rows scroll.
+	viewport_height: Option<f32>,
+	scroll: f32,
+	max_scroll: f32,
+	/// The held navigation key and when it next repeats.
+	held_key: Option<(KeyCode, f64)>,
+}
+
+/// Returns the scroll needed to bring an item spanning [item_top, item_bottom] into the view.
+fn scroll_to_show(
+	scroll: f32, view_top: f32, view_bottom: f32, item_top: f32, item_bottom: f32,
+) -> f32
+{
+	if item_top < view_top
+	{
+		scroll - (view_top - item_top)
+	}
+	else if item_bottom > view_bottom
+	{
+		scroll + (item_bottom - view_bottom)
+	}
+	else
+	{
+		scroll
+	}
 }
 
 impl WidgetList
@@ -598,26 +1336,132 @@ impl WidgetList
 			pos: Point2::new(0., 0.),
 			widgets: new_widgets,
 			cur_selection: cur_selection.expect("No selectable widgets?"),
+			viewport_height: None,
+			scroll: 0.,
+			max_scroll: 0.,
+			held_key: None,
+		}
+	}
+
+	/// The tooltip of the selected widget, if any.
+	pub fn selected_tooltip(&self) -> Option<&str>
+	{
+		self.widgets[self.cur_selection.0][self.cur_selection.1].tooltip()
+	}
+
+	fn viewport(&self) -> Option<(f32, f32)>
+	{
+		self.viewport_height
+			.map(|h| (self.pos.y - h / 2., self.pos.y + h / 2.))
+	}
+
+	fn row_extent(&self, row: usize, s: f32) -> (f32, f32)
+	{
+		let mut top = f32::INFINITY;
+		let mut bottom = -f32::INFINITY;
+		for w in &self.widgets[row]
+		{
+			top = utils::min(top, w.loc().y - s * w.height() / 2.);
+			bottom = utils::max(bottom, w.loc().y + s * w.height() / 2.);
+		}
+		(top, bottom)
+	}
+
+	fn shift(&mut self, dy: f32)
+	{
+		for row in self.widgets.iter_mut()
+		{
+			for w in row.iter_mut()
+			{
+				let mut loc = w.loc();
+				loc.y += dy;
+				w.set_loc(loc);
+			}
+		}
+	}
+
+	fn set_scroll(&mut self, scroll: f32)
+	{
+		let scroll = utils::clamp(scroll, 0., self.max_scroll);
+		self.shift(self.scroll - scroll);
+		self.scroll = scroll;
+	}
+
+	fn scroll_to_selection(&mut self, s: f32)
+	{
+		if let Some((top, bottom)) = self.viewport()
+		{
+			let (row_top, row_bottom) = self.row_extent(self.cur_selection.0, s);
+			self.set_scroll(scroll_to_show(
+				self.scroll,
+				top,
+				bottom,
+				row_top,
+				row_bottom,
+			));
 		}
 	}
 
 	pub fn draw(&self, state: &game_state::GameState)
 	{
-		for row in &self.widgets
+		let s = state.options.ui_scale;
+		let old_clip = state.core.get_clipping_rectangle();
+		if let Some((top, bottom)) = self.viewport()
+		{
+			let transform = state.core.get_current_transform();
+			let (x1, y1) = transform.transform_coordinates(0., top);
+			let (x2, y2) = transform.transform_coordinates(state.buffer_width(), bottom);
+			state.core.set_clipping_rectangle(
+				x1 as i32,
+				y1 as i32,
+				(x2 - x1) as i32,
+				(y2 - y1) as i32,
+			);
+		}
+		for (i, row) in self.widgets.iter().enumerate()
 		{
+			if let Some((top, bottom)) = self.viewport()
+			{
+				let (row_top, row_bottom) = self.row_extent(i, s);
+				if row_bottom < top || row_top > bottom
+				{
+					continue;
+				}
+			}
 			for w in row
 			{
 				w.draw(state);
 			}
 		}
+		state
+			.core
+			.set_clipping_rectangle(old_clip.0, old_clip.1, old_clip.2, old_clip.3);
 	}
 
 	pub fn input(&mut self, state: &mut game_state::GameState, event: &Event) -> Option<Action>
 	{
+		let s = state.options.ui_scale;
 		let mut action = None;
 		let old_selection = self.cur_selection;
+		// Mouse events outside the viewport shouldn't reach the hidden widgets.
+		let mouse_y = match event
+		{
+			Event::MouseAxes { x, y, .. }
+			| Event::MouseButtonDown { x, y, .. }
+			| Event::MouseButtonUp { x, y, .. } => Some(state.transform_mouse(*x as f32, *y as f32).1),
+			_ => None,
+		};
+		let hidden = match (mouse_y, self.viewport())
+		{
+			(Some(y), Some((top, bottom))) => y < top || y > bottom,
+			_ => false,
+		};
 		'got_action: for (i, row) in self.widgets.iter_mut().enumerate()
 		{
+			if hidden
+			{
+				break;
+			}
 			for (j, w) in row.iter_mut().enumerate()
 			{
 				let cur_action = w.input(state, event);
@@ -633,94 +1477,154 @@ impl WidgetList
 				}
 			}
 		}
+		match event
+		{
+			Event::KeyUp { keycode, .. } =>
+			{
+				if self.held_key.map(|(k, _)| k) == Some(*keycode)
+				{
+					self.held_key = None;
+				}
+			}
+			// The KeyUp won't arrive if the key is released while the window is unfocused.
+			Event::DisplaySwitchOut { .. } => self.held_key = None,
+			_ => (),
+		}
 		if action.is_none() || action == Some(Action::SelectMe)
 		{
 			match event
 			{
-				Event::KeyDown { keycode, .. } => match *keycode
+				Event::KeyDown { keycode, .. }
+					if self.widgets[self.cur_selection.0][self.cur_selection.1]
+						.captures_key(*keycode) =>
+				{
+					// Leave these to the selected widget.
+				}
+				Event::KeyDown { keycode, .. } =>
 				{
-					KeyCode::Up =>
+					self.navigate(state, *keycode);
+					if let KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right = *keycode
 					{
-						state.sfx.play_sound("data/ui1.ogg").unwrap();
-						'found1: loop
-						{
-							self.cur_selection.0 = (self.cur_selection.0 + self.widgets.len() - 1)
-								% self.widgets.len();
-							let row_len = self.widgets[self.cur_selection.0].len();
-							if self.cur_selection.1 >= row_len
-							{
-								self.cur_selection.1 = row_len - 1;
-							}
-							for _ in 0..row_len
-							{
-								if self.widgets[self.cur_selection.0][self.cur_selection.1]
-									.selectable()
-								{
-									break 'found1;
-								}
-								self.cur_selection.1 =
-									(self.cur_selection.1 + row_len - 1) % row_len;
-							}
-						}
+						self.held_key = Some((*keycode, state.core.get_time() + NAV_REPEAT_DELAY));
 					}
-					KeyCode::Down =>
+				}
+				Event::TimerTick { .. } =>
+				{
+					if let Some((keycode, repeat_time)) = self.held_key
 					{
-						state.sfx.play_sound("data/ui1.ogg").unwrap();
-						'found2: loop
+						if state.core.get_time() >= repeat_time
 						{
-							self.cur_selection.0 = (self.cur_selection.0 + self.widgets.len() + 1)
-								% self.widgets.len();
-							let row_len = self.widgets[self.cur_selection.0].len();
-							if self.cur_selection.1 >= row_len
-							{
-								self.cur_selection.1 = row_len - 1;
-							}
-							for _ in 0..row_len
-							{
-								if self.widgets[self.cur_selection.0][self.cur_selection.1]
-									.selectable()
-								{
-									break 'found2;
-								}
-								self.cur_selection.1 =
-									(self.cur_selection.1 + row_len - 1) % row_len;
-							}
+							self.navigate(state, keycode);
+							self.held_key = Some((keycode, repeat_time + NAV_REPEAT_INTERVAL));
 						}
 					}
-					KeyCode::Left =>
+				}
+				_ => (),
+			}
+		}
+		if let Event::MouseAxes { dz, .. } = event
+		{
+			if *dz != 0 && (action.is_none() || action == Some(Action::SelectMe))
+			{
+				self.set_scroll(self.scroll - *dz as f32 * s * (BUTTON_HEIGHT + VERT_SPACE));
+			}
+		}
+		if let Event::KeyDown { .. } | Event::TimerTick { .. } = event
+		{
+			if old_selection != self.cur_selection
+			{
+				self.scroll_to_selection(s);
+			}
+		}
+		if old_selection != self.cur_selection
+		{
+			let deselect_action = self.widgets[old_selection.0][old_selection.1].deselect();
+			if action.is_none() || action == Some(Action::SelectMe)
+			{
+				action = deselect_action.or(action);
+			}
+		}
+		self.widgets[old_selection.0][old_selection.1].set_selected(false);
+		self.widgets[self.cur_selection.0][self.cur_selection.1].set_selected(true);
+		action
+	}
+
+	fn navigate(&mut self, state: &mut game_state::GameState, keycode: KeyCode)
+	{
+		match keycode
+		{
+			KeyCode::Up =>
+			{
+				state.sfx.play_sound("data/ui1.ogg").unwrap();
+				'found1: loop
+				{
+					self.cur_selection.0 =
+						(self.cur_selection.0 + self.widgets.len() - 1) % self.widgets.len();
+					let row_len = self.widgets[self.cur_selection.0].len();
+					if self.cur_selection.1 >= row_len
 					{
-						state.sfx.play_sound("data/ui1.ogg").unwrap();
-						let row_len = self.widgets[self.cur_selection.0].len();
-						loop
+						self.cur_selection.1 = row_len - 1;
+					}
+					for _ in 0..row_len
+					{
+						if self.widgets[self.cur_selection.0][self.cur_selection.1].selectable()
 						{
-							self.cur_selection.1 = (self.cur_selection.1 + row_len - 1) % row_len;
-							if self.widgets[self.cur_selection.0][self.cur_selection.1].selectable()
-							{
-								break;
-							}
+							break 'found1;
 						}
+						self.cur_selection.1 = (self.cur_selection.1 + row_len - 1) % row_len;
+					}
+				}
+			}
+			KeyCode::Down =>
+			{
+				state.sfx.play_sound("data/ui1.ogg").unwrap();
+				'found2: loop
+				{
+					self.cur_selection.0 =
+						(self.cur_selection.0 + self.widgets.len() + 1) % self.widgets.len();
+					let row_len = self.widgets[self.cur_selection.0].len();
+					if self.cur_selection.1 >= row_len
+					{
+						self.cur_selection.1 = row_len - 1;
 					}
-					KeyCode::Right =>
+					for _ in 0..row_len
 					{
-						state.sfx.play_sound("data/ui1.ogg").unwrap();
-						let row_len = self.widgets[self.cur_selection.0].len();
-						loop
+						if self.widgets[self.cur_selection.0][self.cur_selection.1].selectable()
 						{
-							self.cur_selection.1 = (self.cur_selection.1 + row_len + 1) % row_len;
-							if self.widgets[self.cur_selection.0][self.cur_selection.1].selectable()
-							{
-								break;
-							}
+							break 'found2;
 						}
+						self.cur_selection.1 = (self.cur_selection.1 + row_len - 1) % row_len;
 					}
-					_ => (),
-				},
-				_ => (),
+				}
 			}
+			KeyCode::Left =>
+			{
+				state.sfx.play_sound("data/ui1.ogg").unwrap();
+				let row_len = self.widgets[self.cur_selection.0].len();
+				loop
+				{
+					self.cur_selection.1 = (self.cur_selection.1 + row_len - 1) % row_len;
+					if self.widgets[self.cur_selection.0][self.cur_selection.1].selectable()
+					{
+						break;
+					}
+				}
+			}
+			KeyCode::Right =>
+			{
+				state.sfx.play_sound("data/ui1.ogg").unwrap();
+				let row_len = self.widgets[self.cur_selection.0].len();
+				loop
+				{
+					self.cur_selection.1 = (self.cur_selection.1 + row_len + 1) % row_len;
+					if self.widgets[self.cur_selection.0][self.cur_selection.1].selectable()
+					{
+						break;
+					}
+				}
+			}
+			_ => (),
 		}
-		self.widgets[old_selection.0][old_selection.1].set_selected(false);
-		self.widgets[self.cur_selection.0][self.cur_selection.1].set_selected(true);
-		action
 	}
 
 	fn resize(&mut self, state: &game_state::GameState)
@@ -791,6 +1695,22 @@ impl WidgetList
 				w.set_loc(loc);
 			}
 		}
+
+		// Align overflowing content to the top of the viewport, then restore the scroll.
+		let old_scroll = self.scroll;
+		self.scroll = 0.;
+		self.max_scroll = 0.;
+		if let Some((top, bottom)) = self.viewport()
+		{
+			let content_top = self.row_extent(0, s).0;
+			let content_bottom = self.row_extent(num_rows - 1, s).1;
+			if content_bottom - content_top > bottom - top
+			{
+				self.shift(top - content_top);
+				self.max_scroll = content_bottom - content_top - (bottom - top);
+			}
+		}
+		self.set_scroll(old_scroll);
 	}
 }
 
@@ -903,9 +1823,7 @@ impl ControlsMenu
 			for i in 0..2
 			{
 				let input = inputs[i];
-				let input_str = input
-					.map(|i| i.to_str().to_string())
-					.unwrap_or("None".into());
+				let input_str = input.map(|i| i.to_string()).unwrap_or("None".into());
 				row.push(Widget::Button(Button::new(
 					w,
 					h,
@@ -1038,6 +1956,8 @@ impl ControlsMenu
 		let cy = state.buffer_height() / 2.;
 		self.widgets.pos.x = cx;
 		self.widgets.pos.y = cy;
+		self.widgets.viewport_height =
+			Some(state.buffer_height() - 2. * state.options.ui_scale * VERT_SPACE);
 		self.widgets.resize(state);
 	}
 }
@@ -1055,74 +1975,71 @@ impl OptionsMenu
 		let h = BUTTON_HEIGHT;
 
 		let widgets = [
-			vec![
-				Widget::Label(Label::new(w, h, "Fullscreen")),
-				Widget::Toggle(Toggle::new(
-					w,
-					h,
-					state.options.fullscreen as usize,
-					vec!["No".into(), "Yes".into()],
-					|_| Action::ToggleFullscreen,
-				)),
-			],
-			vec![
-				Widget::Label(Label::new(w, h, "Fractional Scale")),
-				Widget::Toggle(Toggle::new(
-					w,
-					h,
-					state.options.frac_scale as usize,
-					vec!["No".into(), "Yes".into()],
-					|_| Action::ToggleFracScale,
-				)),
-			],
+			vec![Widget::Checkbox(
+				Checkbox::builder("Fullscreen", state.options.fullscreen, |v| {
+					Action::Fullscreen(v)
+				})
+				.build(),
+			)],
+			vec![Widget::Checkbox(
+				Checkbox::builder("Fractional Scale", state.options.frac_scale, |v| {
+					Action::FracScale(v)
+				})
+				.tooltip("Allow non-integer scaling of the game to fill the window")
+				.build(),
+			)],
 			vec![
 				Widget::Label(Label::new(w, h, "Music")),
-				Widget::Slider(Slider::new(
-					w,
-					h,
-					state.options.music_volume,
-					0.,
-					4.,
-					0.1,
-					|i| Action::MusicVolume(i),
-				)),
+				Widget::Slider(
+					Slider::builder(state.options.music_volume, 0., 4., |i| {
+						Action::MusicVolume(i)
+					})
+					.build(),
+				),
 			],
 			vec![
 				Widget::Label(Label::new(w, h, "SFX")),
-				Widget::Slider(Slider::new(
-					w,
-					h,
-					state.options.music_volume,
-					0.,
-					4.,
-					0.1,
-					|i| Action::SfxVolume(i),
-				)),
+				Widget::Slider(
+					Slider::builder(state.options.music_volume, 0., 4., |i| Action::SfxVolume(i))
+						.build(),
+				),
 			],
 			vec![
 				Widget::Label(Label::new(w, h, "UI Scale")),
-				Widget::Slider(Slider::new(
-					w,
-					h,
-					state.options.ui_scale,
-					1.,
-					4.,
-					0.25,
-					|i| Action::UiScale(i),
-				)),
+				Widget::Slider(
+					Slider::builder(state.options.ui_scale, 1., 4., |i| Action::UiScale(i))
+						.round_to(0.25)
+						.build(),
+				),
 			],
 			vec![
 				Widget::Label(Label::new(w, h, "Scroll")),
-				Widget::Slider(Slider::new(
+				Widget::Slider(Slider::new_int(
 					w,
 					h,
-					state.options.camera_speed as f32,
-					1.,
-					10.,
-					1.,
-					|i| Action::CameraSpeed(i as i32),
+					state.options.camera_speed,
+					1,
+					10,
+					1,
+					|i| Action::CameraSpeed(i),
 				)),
 			],
+			vec![
+				Widget::Label(Label::new(w, h, "Seed")),
+				Widget::TextField(
+					TextField::builder(
+						&state
+							.options
+							.seed
+							.map(|s| s.to_string())
+							.unwrap_or_default(),
+						|s| Action::Seed(s),
+					)
+					.max_len(20)
+					.tooltip("Leave empty for a random seed. Takes effect on restart")
+					.build(),
+				),
+			],
 			vec![Widget::Button(Button::new(w, h, "Back", Action::Back))],
 		];
 
@@ -1136,6 +2053,19 @@ impl OptionsMenu
 	pub fn draw(&self, state: &game_state::GameState)
 	{
 		self.widgets.draw(state);
+
+		if let Some(tooltip) = self.widgets.selected_tooltip()
+		{
+			let lh = state.ui_font().get_line_height() as f32;
+			state.core.draw_text(
+				state.ui_font(),
+				LABEL,
+				state.buffer_width() / 2.,
+				state.buffer_height() - lh - VERT_SPACE,
+				FontAlign::Centre,
+				tooltip,
+			);
+		}
 	}
 
 	pub fn input(&mut self, state: &mut game_state::GameState, event: &Event) -> Option<Action>
@@ -1145,13 +2075,13 @@ impl OptionsMenu
 		{
 			match action
 			{
-				Action::ToggleFullscreen =>
+				Action::Fullscreen(v) =>
 				{
-					state.options.fullscreen = !state.options.fullscreen;
+					state.options.fullscreen = v;
 				}
-				Action::ToggleFracScale =>
+				Action::FracScale(v) =>
 				{
-					state.options.frac_scale = !state.options.frac_scale;
+					state.options.frac_scale = v;
 				}
 				Action::MusicVolume(v) =>
 				{
@@ -1171,6 +2101,10 @@ impl OptionsMenu
 				{
 					state.options.ui_scale = v;
 				}
+				Action::Seed(text) =>
+				{
+					state.options.seed = text.trim().parse().ok();
+				}
 				Action::Back =>
 				{
 					game_state::save_options(&state.core, &state.options).unwrap();
@@ -1393,3 +2327,185 @@ impl SubScreens
 		self.subscreens.is_empty()
 	}
 }
+
+#[test]
+fn test_log_slider_midpoint()
+{
+	let slider = Slider::new_log(BUTTON_WIDTH, BUTTON_HEIGHT, 1., 0.01, 100., 0.01, |v| {
+		Action::MusicVolume(v)
+	});
+	let mid = slider.frac_to_value(0.5);
+	assert!((mid - (0.01_f32 * 100.).sqrt()).abs() < 1e-4);
+	assert!((slider.value_to_frac(mid) - 0.5).abs() < 1e-4);
+	assert!((slider.frac_to_value(0.) - 0.01).abs() < 1e-6);
+	assert!((slider.frac_to_value(1.) - 100.).abs() < 1e-3);
+}
+
+#[test]
+fn test_log_slider_step()
+{
+	let mut slider = Slider::new_log(BUTTON_WIDTH, BUTTON_HEIGHT, 1., 0.01, 100., 0.01, |v| {
+		Action::MusicVolume(v)
+	});
+	slider.step(1.);
+	assert!((slider.cur_pos - 1.58).abs() < 1e-4);
+	slider.step(-1.);
+	assert!((slider.cur_pos - 1.).abs() < 1e-4);
+	slider.step(-100.);
+	assert!((slider.cur_pos - 0.01).abs() < 1e-6);
+
+	// The fraction step is smaller than `round_to` here.
+	let mut slider = Slider::new_log(BUTTON_WIDTH, BUTTON_HEIGHT, 1., 1., 2., 0.5, |v| {
+		Action::MusicVolume(v)
+	});
+	slider.step(1.);
+	assert_eq!(slider.cur_pos, 1.5);
+	slider.step(1.);
+	assert_eq!(slider.cur_pos, 2.);
+	slider.step(1.);
+	assert_eq!(slider.cur_pos, 2.);
+	slider.step(-1.);
+	assert_eq!(slider.cur_pos, 1.5);
+}
+
+#[test]
+fn test_text_field_editing()
+{
+	let mut field = TextField::new(BUTTON_WIDTH, BUTTON_HEIGHT, "ab", 4, |s| Action::Seed(s));
+	assert_eq!(field.cursor, 2);
+	field.cursor = 1;
+	field.insert_char('é');
+	assert_eq!(field.text, "aéb");
+	field.insert_char('\n');
+	field.insert_char('c');
+	field.insert_char('d');
+	assert_eq!(field.text, "aécb");
+	field.backspace();
+	field.delete();
+	assert_eq!(field.text, "aé");
+	assert_eq!(field.cursor, 2);
+	assert_eq!(field.commit(), Some(Action::Seed("aé".into())));
+	assert_eq!(field.commit(), None);
+}
+
+#[test]
+fn test_checkbox_toggle()
+{
+	let mut checkbox = Checkbox::new(BUTTON_WIDTH, BUTTON_HEIGHT, "Test", false, |v| {
+		Action::Fullscreen(v)
+	});
+	assert_eq!(checkbox.toggle(), Action::Fullscreen(true));
+	assert!(checkbox.checked);
+	assert_eq!(checkbox.toggle(), Action::Fullscreen(false));
+	assert!(!checkbox.checked);
+}
+
+#[test]
+fn test_slider_step_clamps()
+{
+	let mut slider = Slider::new(BUTTON_WIDTH, BUTTON_HEIGHT, 1., 0., 4., 0.5, |v| {
+		Action::MusicVolume(v)
+	});
+	slider.step(3.);
+	assert_eq!(slider.cur_pos, 2.5);
+	slider.step(5.);
+	assert_eq!(slider.cur_pos, 4.);
+	slider.step(-20.);
+	assert_eq!(slider.cur_pos, 0.);
+}
+
+#[test]
+fn test_scroll_to_show()
+{
+	// Already visible.
+	assert_eq!(scroll_to_show(10., 0., 100., 20., 40.), 10.);
+	// Above the view.
+	assert_eq!(scroll_to_show(10., 0., 100., -15., 5.), -5.);
+	// Below the view.
+	assert_eq!(scroll_to_show(10., 0., 100., 90., 120.), 30.);
+}
+
+#[test]
+fn test_int_slider()
+{
+	let mut slider = Slider::new_int(BUTTON_WIDTH, BUTTON_HEIGHT, 3, 1, 10, 1, |i| {
+		Action::CameraSpeed(i)
+	});
+	slider.step(1.);
+	assert_eq!(slider.action(), Action::CameraSpeed(4));
+	slider.cur_pos = slider.frac_to_value(0.5);
+	slider.round_cur_pos();
+	assert_eq!(slider.action(), Action::CameraSpeed(6));
+}
+
+#[test]
+fn test_selected_tooltip()
+{
+	let widgets = WidgetList::new(&[
+		&[Widget::Label(
+			Label::builder("Label").tooltip("Hidden").build(),
+		)],
+		&[Widget::Button(
+			Button::builder("Back", Action::Back)
+				.tooltip("Go back")
+				.build(),
+		)],
+	]);
+	assert_eq!(widgets.selected_tooltip(), Some("Go back"));
+}
+
+#[test]
+fn test_widget_builder()
+{
+	let button = Button::builder("Back", Action::Back).build();
+	assert_eq!(button.size, Vector2::new(BUTTON_WIDTH, BUTTON_HEIGHT));
+	assert_eq!(button.tooltip, None);
+
+	let slider = Slider::builder(1., 0.5, 4., |v| Action::UiScale(v))
+		.width(32.)
+		.round_to(0.25)
+		.log_scale()
+		.tooltip("Scale")
+		.build();
+	assert_eq!(slider.size, Vector2::new(32., BUTTON_HEIGHT));
+	assert_eq!(slider.round_to, 0.25);
+	assert!(slider.log_scale);
+	assert_eq!(slider.tooltip.as_deref(), Some("Scale"));
+
+	let field = TextField::builder("abcdef", |s| Action::Seed(s))
+		.max_len(3)
+		.build();
+	assert_eq!(field.text, "abc");
+	assert_eq!(field.cursor, 3);
+
+	let long_text = "a".repeat(40);
+	let field = TextField::builder(&long_text, |s| Action::Seed(s))
+		.max_len(64)
+		.build();
+	assert_eq!(field.text, long_text);
+	let field = TextField::builder(&long_text, |s| Action::Seed(s)).build();
+	assert_eq!(field.text.len(), 32);
+}
+
+#[test]
+fn test_icon_button_click()
+{
+	let mut text = Button::new(BUTTON_WIDTH, BUTTON_HEIGHT, "Back", Action::Back);
+	let mut icon = Button::new_icon(
+		BUTTON_WIDTH,
+		BUTTON_HEIGHT,
+		"data/back.png",
+		"Back",
+		Action::Back,
+	);
+	for button in [&mut text, &mut icon]
+	{
+		button.loc = Point2::new(100., 100.);
+		assert_eq!(button.click(1., 100., 100.), Some(Action::Back));
+		assert_eq!(
+			button.click(2., 100. + BUTTON_WIDTH * 0.75, 100.),
+			Some(Action::Back)
+		);
+		assert_eq!(button.click(1., 100. + BUTTON_WIDTH * 0.75, 100.), None);
+	}
+}
@@ -9,6 +9,7 @@ use rand::prelude::*;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 use slr_config::{from_element, to_element, ConfigElement, Source};
+use std::collections::VecDeque;
 use std::{fs, path};
 
 pub const DT: f32 = 1. / 60.;
@@ -22,6 +23,20 @@ use na::{
 };
 use nalgebra as na;
 
+/// Whether to draw a frame at `time`, given the time of the last drawn frame. While unfocused,
+/// drawing is capped to `unfocused_fps`, if set.
+pub fn should_draw(
+	time: f64, last_draw_time: f64, focused: bool, unfocused_fps: Option<u32>,
+) -> bool
+{
+	match (focused, unfocused_fps)
+	{
+		(false, Some(0)) => false,
+		(false, Some(fps)) => time - last_draw_time >= 1. / fps as f64,
+		_ => true,
+	}
+}
+
 pub fn projection_transform(dw: f32, dh: f32) -> Perspective3<f32>
 {
 	Perspective3::new(dw / dh, f32::pi() / 2., 1., 2000.)
@@ -48,12 +63,96 @@ pub fn camera_project(x: f32, y: f32, z: f32, player_x: f32, player_z: f32) -> I
 	view
 }
 
+/// Creates the game's RNG. Passing a seed makes the run reproducible.
+pub fn make_rng(seed: Option<u64>) -> StdRng
+{
+	match seed
+	{
+		Some(seed) => StdRng::seed_from_u64(seed),
+		None => StdRng::from_entropy(),
+	}
+}
+
 pub fn random_color(seed: u64, saturation: f32, value: f32) -> Color
 {
 	let mut rng = StdRng::seed_from_u64(seed);
 	Color::from_hsv(rng.gen_range(0. ..360.), saturation, value)
 }
 
+/// Parses `#RGB`, `#RRGGBB` or `#RRGGBBAA` hex colors. The `#` is optional.
+pub fn parse_hex_color(s: &str) -> Option<Color>
+{
+	let s = s.trim();
+	let s = s.strip_prefix('#').unwrap_or(s);
+	if !s.chars().all(|c| c.is_ascii_hexdigit())
+	{
+		return None;
+	}
+	let channel = |i: usize, len: usize| {
+		let v = u8::from_str_radix(&s[i * len..(i + 1) * len], 16).unwrap();
+		if len == 1
+		{
+			v * 17
+		}
+		else
+		{
+			v
+		}
+	};
+	match s.len()
+	{
+		3 => Some(Color::from_rgb(channel(0, 1), channel(1, 1), channel(2, 1))),
+		6 => Some(Color::from_rgb(channel(0, 2), channel(1, 2), channel(2, 2))),
+		8 => Some(Color::from_rgba(
+			channel(0, 2),
+			channel(1, 2),
+			channel(2, 2),
+			channel(3, 2),
+		)),
+		_ => None,
+	}
+}
+
+/// Formats a color as `#RRGGBB`, or `#RRGGBBAA` if it's not opaque.
+pub fn color_to_hex(color: Color) -> String
+{
+	let (r, g, b, a) = color.to_rgba_f();
+	let to_u8 = |v: f32| (clamp(v, 0., 1.) * 255.).round() as u8;
+	if to_u8(a) == 255
+	{
+		format!("#{:02X}{:02X}{:02X}", to_u8(r), to_u8(g), to_u8(b))
+	}
+	else
+	{
+		format!(
+			"#{:02X}{:02X}{:02X}{:02X}",
+			to_u8(r),
+			to_u8(g),
+			to_u8(b),
+			to_u8(a)
+		)
+	}
+}
+
+/// Serializes colors as hex strings, use with `#[serde(with = "utils::hex_color")]`.
+pub mod hex_color
+{
+	use allegro::Color;
+	use serde::{Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error>
+	{
+		serializer.serialize_str(&super::color_to_hex(*color))
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error>
+	{
+		let s = String::deserialize(deserializer)?;
+		super::parse_hex_color(&s)
+			.ok_or_else(|| serde::de::Error::custom(format!("Invalid color '{}'", s)))
+	}
+}
+
 pub trait ColorExt
 {
 	fn interpolate(&self, other: Color, f: f32) -> Color;
@@ -115,6 +214,27 @@ pub fn clamp<T: PartialOrd>(x: T, min: T, max: T) -> T
 	}
 }
 
+/// Linearly maps `value` from [in_min, in_max] to [out_min, out_max]. Values outside the input
+/// range are extrapolated.
+pub fn remap(value: f32, in_min: f32, in_max: f32, out_min: f32, out_max: f32) -> f32
+{
+	out_min + (value - in_min) / (in_max - in_min) * (out_max - out_min)
+}
+
+/// Like `remap`, but the result is clamped to the output range.
+pub fn remap_clamped(value: f32, in_min: f32, in_max: f32, out_min: f32, out_max: f32) -> f32
+{
+	let ret = remap(value, in_min, in_max, out_min, out_max);
+	if out_min < out_max
+	{
+		clamp(ret, out_min, out_max)
+	}
+	else
+	{
+		clamp(ret, out_max, out_min)
+	}
+}
+
 pub fn round_point(vec: Point2<f32>) -> Point2<f32>
 {
 	Point2::new(vec.x.round(), vec.y.round())
@@ -125,6 +245,279 @@ pub fn sigmoid(x: f32) -> f32
 	1. / (1. + (-x).exp())
 }
 
+pub fn ease_linear(t: f32) -> f32
+{
+	t
+}
+
+pub fn ease_in_quad(t: f32) -> f32
+{
+	t * t
+}
+
+pub fn ease_out_quad(t: f32) -> f32
+{
+	t * (2. - t)
+}
+
+pub fn ease_in_out_quad(t: f32) -> f32
+{
+	if t < 0.5
+	{
+		2. * t * t
+	}
+	else
+	{
+		-1. + (4. - 2. * t) * t
+	}
+}
+
+pub fn ease_in_expo(t: f32) -> f32
+{
+	if t <= 0.
+	{
+		0.
+	}
+	else
+	{
+		2.0f32.powf(10. * t - 10.)
+	}
+}
+
+pub fn ease_out_expo(t: f32) -> f32
+{
+	if t >= 1.
+	{
+		1.
+	}
+	else
+	{
+		1. - 2.0f32.powf(-10. * t)
+	}
+}
+
+/// Values that can be linearly interpolated.
+pub trait Lerp: Copy
+{
+	fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32
+{
+	fn lerp(self, other: Self, t: f32) -> Self
+	{
+		self + (other - self) * t
+	}
+}
+
+impl Lerp for Vector2<f32>
+{
+	fn lerp(self, other: Self, t: f32) -> Self
+	{
+		self + (other - self) * t
+	}
+}
+
+impl Lerp for Vector3<f32>
+{
+	fn lerp(self, other: Self, t: f32) -> Self
+	{
+		self + (other - self) * t
+	}
+}
+
+impl Lerp for Point2<f32>
+{
+	fn lerp(self, other: Self, t: f32) -> Self
+	{
+		self + (other - self) * t
+	}
+}
+
+impl Lerp for Point3<f32>
+{
+	fn lerp(self, other: Self, t: f32) -> Self
+	{
+		self + (other - self) * t
+	}
+}
+
+/// Animates a value from `start` to `end` over `duration` seconds, shaped by `easing`.
+#[derive(Clone, Debug)]
+pub struct Tween<T: Lerp>
+{
+	pub start: T,
+	pub end: T,
+	pub duration: f32,
+	elapsed: f32,
+	easing: fn(f32) -> f32,
+}
+
+impl<T: Lerp> Tween<T>
+{
+	pub fn new(start: T, end: T, duration: f32, easing: fn(f32) -> f32) -> Self
+	{
+		Self {
+			start: start,
+			end: end,
+			duration: duration,
+			elapsed: 0.,
+			easing: easing,
+		}
+	}
+
+	pub fn update(&mut self, dt: f32)
+	{
+		self.elapsed = min(self.elapsed + dt, self.duration);
+	}
+
+	pub fn value(&self) -> T
+	{
+		if self.finished()
+		{
+			return self.end;
+		}
+		let t = (self.easing)(self.elapsed / self.duration);
+		self.start.lerp(self.end, t)
+	}
+
+	pub fn finished(&self) -> bool
+	{
+		self.elapsed >= self.duration
+	}
+}
+
+/// Accumulates real time into fixed logic steps of `dt` seconds.
+///
+/// At most `max_steps` steps are run per `advance`. When the logic falls further behind than
+/// that, the excess whole steps are dropped so the game slows down instead of spiraling, while
+/// the partial step is kept so `alpha` stays consistent.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep
+{
+	dt: f64,
+	max_steps: u32,
+	last_time: f64,
+	accumulator: f64,
+}
+
+impl FixedTimestep
+{
+	pub fn new(dt: f64, max_steps: u32, time: f64) -> Self
+	{
+		Self {
+			dt: dt,
+			max_steps: max_steps,
+			last_time: time,
+			accumulator: 0.,
+		}
+	}
+
+	/// Advances the clock to `time`, returning how many logic steps to run.
+	pub fn advance(&mut self, time: f64) -> u32
+	{
+		self.accumulator += max(time - self.last_time, 0.);
+		self.last_time = time;
+		let steps = (self.accumulator / self.dt).floor();
+		self.accumulator -= steps * self.dt;
+		min(steps as u32, self.max_steps)
+	}
+
+	/// How far `time` is between the last and the next logic step, in [0, 1].
+	pub fn alpha(&self, time: f64) -> f32
+	{
+		let elapsed = self.accumulator + max(time - self.last_time, 0.);
+		clamp(elapsed / self.dt, 0., 1.) as f32
+	}
+}
+
+/// Rolling averages of the frame timings, for the FPS overlay.
+#[derive(Clone, Debug)]
+pub struct FrameStats
+{
+	/// Pairs of frame time and the number of logic ticks run for that frame.
+	samples: VecDeque<(f64, i32)>,
+	max_samples: usize,
+}
+
+impl FrameStats
+{
+	pub fn new(max_samples: usize) -> Self
+	{
+		Self {
+			samples: VecDeque::with_capacity(max_samples),
+			max_samples: max_samples,
+		}
+	}
+
+	pub fn record(&mut self, frame_time: f64, num_logics: i32)
+	{
+		if self.samples.len() >= self.max_samples
+		{
+			self.samples.pop_front();
+		}
+		self.samples.push_back((frame_time, num_logics));
+	}
+
+	/// Average frame time, in seconds.
+	pub fn frame_time(&self) -> f64
+	{
+		if self.samples.is_empty()
+		{
+			return 0.;
+		}
+		self.samples.iter().map(|s| s.0).sum::<f64>() / self.samples.len() as f64
+	}
+
+	pub fn fps(&self) -> f64
+	{
+		let frame_time = self.frame_time();
+		if frame_time > 0.
+		{
+			1. / frame_time
+		}
+		else
+		{
+			0.
+		}
+	}
+
+	/// Average number of logic ticks per drawn frame.
+	pub fn logics_per_draw(&self) -> f32
+	{
+		if self.samples.is_empty()
+		{
+			return 0.;
+		}
+		self.samples.iter().map(|s| s.1).sum::<i32>() as f32 / self.samples.len() as f32
+	}
+}
+
+/// Saves `bitmap` to `path`, creating the parent directories as needed. The format is picked
+/// from the extension, so the image addon must be initialized.
+pub fn save_screenshot(bitmap: &Bitmap, path: &str) -> Result<()>
+{
+	use std::ffi::CString;
+	if let Some(parent) = path::Path::new(path).parent()
+	{
+		fs::create_dir_all(parent).map_err(|e| {
+			Error::new(
+				format!("Couldn't create directory for '{}'", path),
+				Some(Box::new(e)),
+			)
+		})?;
+	}
+	let c_path = CString::new(path.as_bytes()).map_err(|_| format!("Invalid path '{}'", path))?;
+	if unsafe { allegro_sys::al_save_bitmap(c_path.as_ptr(), bitmap.get_allegro_bitmap()) != 0 }
+	{
+		Ok(())
+	}
+	else
+	{
+		Err(format!("Couldn't save '{}'", path).into())
+	}
+}
+
 pub fn read_to_string(path: &str) -> Result<String>
 {
 	fs::read_to_string(path)
@@ -141,11 +534,33 @@ pub fn load_config<T: DeserializeOwned + Clone>(file: &str) -> Result<T>
 		.map_err(|e| Error::new(format!("Config parsing error"), Some(Box::new(e))))
 }
 
+/// Writes the config to a temporary file first and then renames it over `file`, so a failed
+/// write never leaves a partially written config behind.
 pub fn save_config<T: Serialize>(file: &str, val: T) -> Result<()>
+{
+	save_config_impl(file, val, false)
+}
+
+/// Like `save_config`, but also keeps the previous version of the file as `{file}.bak`.
+pub fn save_config_with_backup<T: Serialize>(file: &str, val: T) -> Result<()>
+{
+	save_config_impl(file, val, true)
+}
+
+fn save_config_impl<T: Serialize>(file: &str, val: T, keep_backup: bool) -> Result<()>
 {
 	let element = to_element(&val)
 		.map_err(|e| Error::new(format!("Config writing error"), Some(Box::new(e))))?;
-	std::fs::write(file, format!("{}", element))
+	let tmp_file = format!("{}.tmp", file);
+	fs::write(&tmp_file, format!("{}", element))
+		.map_err(|e| Error::new(format!("Couldn't write '{}'", tmp_file), Some(Box::new(e))))?;
+	if keep_backup && path::Path::new(file).exists()
+	{
+		let bak_file = format!("{}.bak", file);
+		fs::copy(file, &bak_file)
+			.map_err(|e| Error::new(format!("Couldn't write '{}'", bak_file), Some(Box::new(e))))?;
+	}
+	fs::rename(&tmp_file, file)
 		.map_err(|e| Error::new(format!("Couldn't write '{}'", file), Some(Box::new(e))))?;
 	Ok(())
 }
@@ -169,27 +584,81 @@ pub fn load_ttf_font(ttf: &TtfAddon, file: &str, size: i32) -> Result<Font>
 
 pub fn load_shader(disp: &mut Display, path: &str) -> Result<std::sync::Weak<Shader>>
 {
-	let shader = disp.create_shader(ShaderPlatform::GLSL).unwrap();
+	let vertex = read_to_string(&format!("{path}_vertex.glsl"))?;
+	let pixel = read_to_string(&format!("{path}_pixel.glsl"))?;
+	load_shader_source(disp, path, &vertex, &pixel)
+}
 
-	shader
-		.upgrade()
-		.unwrap()
-		.attach_shader_source(
-			ShaderType::Vertex,
-			Some(&read_to_string(&format!("{path}_vertex.glsl"))?),
-		)
-		.unwrap();
+/// Like `load_shader`, but takes the GLSL sources directly. `name` is only used for error
+/// messages.
+pub fn load_shader_source(
+	disp: &mut Display, name: &str, vertex: &str, pixel: &str,
+) -> Result<std::sync::Weak<Shader>>
+{
+	let shader = disp
+		.create_shader(ShaderPlatform::GLSL)
+		.map_err(|_| format!("Couldn't create shader '{}'", name))?;
+	build_shader(&shader.upgrade().unwrap(), name, vertex, pixel)?;
+	Ok(shader)
+}
+
+/// Rebuilds shaders in place from disk, e.g. for hot-reloading.
+///
+/// The new sources are first built into a scratch shader, which is created once and reused. The
+/// live shader is only touched if that succeeds, so a broken edit keeps the old program running.
+pub struct ShaderReloader
+{
+	scratch: Option<std::sync::Weak<Shader>>,
+}
+
+impl ShaderReloader
+{
+	pub fn new() -> Self
+	{
+		Self { scratch: None }
+	}
+
+	/// Rebuilds `shader` from `{path}_vertex.glsl` and `{path}_pixel.glsl`. On failure the error
+	/// is returned and `shader` is left as it was.
+	pub fn reload(
+		&mut self, disp: &mut Display, shader: &std::sync::Weak<Shader>, path: &str,
+	) -> Result<()>
+	{
+		let vertex = read_to_string(&format!("{path}_vertex.glsl"))?;
+		let pixel = read_to_string(&format!("{path}_pixel.glsl"))?;
+		let shader = shader
+			.upgrade()
+			.ok_or_else(|| format!("Shader '{}' was destroyed", path))?;
+
+		let scratch = match self.scratch.as_ref().and_then(|s| s.upgrade())
+		{
+			Some(scratch) => scratch,
+			None =>
+			{
+				let scratch = disp
+					.create_shader(ShaderPlatform::GLSL)
+					.map_err(|_| "Couldn't create the scratch shader".to_string())?;
+				self.scratch = Some(scratch.clone());
+				scratch.upgrade().unwrap()
+			}
+		};
+		build_shader(&scratch, path, &vertex, &pixel)?;
+		build_shader(&shader, path, &vertex, &pixel)
+	}
+}
 
+fn build_shader(shader: &Shader, name: &str, vertex: &str, pixel: &str) -> Result<()>
+{
 	shader
-		.upgrade()
-		.unwrap()
-		.attach_shader_source(
-			ShaderType::Pixel,
-			Some(&read_to_string(&format!("{path}_pixel.glsl"))?),
-		)
-		.unwrap();
-	shader.upgrade().unwrap().build().unwrap();
-	Ok(shader)
+		.attach_shader_source(ShaderType::Vertex, Some(vertex))
+		.map_err(|log| format!("Couldn't compile vertex shader '{}':\n{}", name, log))?;
+	shader
+		.attach_shader_source(ShaderType::Pixel, Some(pixel))
+		.map_err(|log| format!("Couldn't compile pixel shader '{}':\n{}", name, log))?;
+	shader
+		.build()
+		.map_err(|log| format!("Couldn't link shader '{}':\n{}", name, log))?;
+	Ok(())
 }
 
 pub fn nearest_line_point(v1: Point2<f32>, v2: Point2<f32>, test_point: Point2<f32>)
@@ -238,6 +707,29 @@ pub fn nearest_poly_point(vs: &[Point2<f32>], test_point: Point2<f32>) -> Point2
 	best_point
 }
 
+/// Shoelace area of the polygon. Negative for clockwise winding (with y up), which is what
+/// `is_inside_poly` expects.
+pub fn polygon_area_signed(vs: &[Point2<f32>]) -> f32
+{
+	let mut area = 0.;
+	for idx in 0..vs.len()
+	{
+		let v1 = vs[idx];
+		let v2 = vs[(idx + 1) % vs.len()];
+		area += v1.x * v2.y - v2.x * v1.y;
+	}
+	area / 2.
+}
+
+/// Reverses the polygon if needed so that it has the winding `is_inside_poly` expects.
+pub fn ensure_clockwise(vs: &mut Vec<Point2<f32>>)
+{
+	if polygon_area_signed(vs) > 0.
+	{
+		vs.reverse();
+	}
+}
+
 pub fn is_inside_poly(vs: &[Point2<f32>], test_point: Point2<f32>) -> bool
 {
 	// Clockwise.
@@ -364,6 +856,103 @@ fn test_nearest_line_point()
 	assert!((n - Point2::new(2., 3.)).norm() < 1e-3);
 }
 
+#[test]
+fn test_remap()
+{
+	assert!((remap(5., 0., 10., 100., 200.) - 150.).abs() < 1e-4);
+	assert!((remap(-5., 0., 10., 100., 200.) - 50.).abs() < 1e-4);
+	assert!((remap(15., 0., 10., 100., 200.) - 250.).abs() < 1e-4);
+
+	assert!((remap_clamped(5., 0., 10., 100., 200.) - 150.).abs() < 1e-4);
+	assert!((remap_clamped(-5., 0., 10., 100., 200.) - 100.).abs() < 1e-4);
+	assert!((remap_clamped(15., 0., 10., 100., 200.) - 200.).abs() < 1e-4);
+
+	// Reversed output range.
+	assert!((remap_clamped(-5., 0., 10., 1., 0.) - 1.).abs() < 1e-4);
+	assert!((remap_clamped(15., 0., 10., 1., 0.) - 0.).abs() < 1e-4);
+}
+
+#[test]
+fn test_tween()
+{
+	let mut tween = Tween::new(1., 3., 1., ease_linear);
+	assert_eq!(tween.value(), 1.);
+	assert!(!tween.finished());
+
+	for _ in 0..4
+	{
+		tween.update(0.25);
+	}
+	assert!(tween.finished());
+	assert_eq!(tween.value(), 3.);
+
+	tween.update(0.25);
+	assert_eq!(tween.value(), 3.);
+
+	let mut tween = Tween::new(Point2::new(0., 0.), Point2::new(4., 8.), 2., ease_linear);
+	tween.update(1.);
+	assert!((tween.value() - Point2::new(2., 4.)).norm() < 1e-4);
+}
+
+#[test]
+fn test_save_config_failure()
+{
+	use std::collections::BTreeMap;
+
+	let dir = std::env::temp_dir().join(format!("hackinit_test_save_{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	let file = dir.join("options.cfg");
+	let file = file.to_str().unwrap();
+
+	let mut val = BTreeMap::new();
+	val.insert("a".to_string(), 1);
+	save_config_with_backup(file, &val).unwrap();
+	let original = fs::read_to_string(file).unwrap();
+
+	// Make the temporary file unwritable by putting a directory in its place.
+	fs::create_dir_all(format!("{}.tmp", file)).unwrap();
+	val.insert("a".to_string(), 2);
+	assert!(save_config_with_backup(file, &val).is_err());
+	assert_eq!(fs::read_to_string(file).unwrap(), original);
+
+	// A successful write keeps the old version as a backup.
+	fs::remove_dir(format!("{}.tmp", file)).unwrap();
+	save_config_with_backup(file, &val).unwrap();
+	assert_eq!(
+		fs::read_to_string(format!("{}.bak", file)).unwrap(),
+		original
+	);
+	assert_ne!(fs::read_to_string(file).unwrap(), original);
+
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_should_draw()
+{
+	fn count_draws(focused: bool, unfocused_fps: Option<u32>) -> i32
+	{
+		let mut draws = 0;
+		let mut last_draw_time = -1.;
+		for i in 0..60
+		{
+			let time = i as f64 * DT as f64;
+			if should_draw(time, last_draw_time, focused, unfocused_fps)
+			{
+				last_draw_time = time;
+				draws += 1;
+			}
+		}
+		draws
+	}
+
+	assert_eq!(count_draws(true, Some(10)), 60);
+	assert_eq!(count_draws(false, None), 60);
+	let unfocused_draws = count_draws(false, Some(10));
+	assert!(unfocused_draws >= 9 && unfocused_draws <= 11);
+	assert_eq!(count_draws(false, Some(0)), 0);
+}
+
 #[test]
 fn test_is_inside_poly()
 {
@@ -386,6 +975,30 @@ fn test_is_inside_poly()
 	assert!(is_inside_poly(&vs, Point2::new(0., 0.)));
 }
 
+#[test]
+fn test_polygon_winding()
+{
+	let cw = vec![
+		Point2::new(0., 0.),
+		Point2::new(0., 3.),
+		Point2::new(3., 3.),
+		Point2::new(3., 0.),
+	];
+	let mut ccw = cw.clone();
+	ccw.reverse();
+
+	assert!((polygon_area_signed(&cw) + 9.).abs() < 1e-4);
+	assert!((polygon_area_signed(&ccw) - 9.).abs() < 1e-4);
+
+	assert!(!is_inside_poly(&ccw, Point2::new(1., 1.)));
+	ensure_clockwise(&mut ccw);
+	assert!(is_inside_poly(&ccw, Point2::new(1., 1.)));
+
+	let mut cw2 = cw.clone();
+	ensure_clockwise(&mut cw2);
+	assert_eq!(cw, cw2);
+}
+
 #[test]
 fn test_segment_segment()
 {
@@ -409,3 +1022,60 @@ fn test_segment_segment2()
 
 	assert!(intersect_segment_segment(start1, end1, start2, end2));
 }
+
+#[test]
+fn test_frame_stats()
+{
+	let mut stats = FrameStats::new(2);
+	assert_eq!(stats.fps(), 0.);
+	stats.record(1., 4);
+	stats.record(0.5, 1);
+	stats.record(0.5, 1);
+	assert_eq!(stats.frame_time(), 0.5);
+	assert_eq!(stats.fps(), 2.);
+	assert_eq!(stats.logics_per_draw(), 1.);
+}
+
+#[test]
+fn test_fixed_timestep()
+{
+	let mut timestep = FixedTimestep::new(0.25, 4, 10.);
+	assert_eq!(timestep.advance(10.1), 0);
+	assert_eq!(timestep.advance(10.5), 2);
+	assert!((timestep.alpha(10.5) - 0.).abs() < 1e-5);
+	assert!((timestep.alpha(10.6) - 0.4).abs() < 1e-5);
+
+	// A long stall is capped, but the partial step survives.
+	assert_eq!(timestep.advance(20.6), 4);
+	assert!((timestep.alpha(20.6) - 0.4).abs() < 1e-5);
+	assert_eq!(timestep.advance(20.6), 0);
+	assert_eq!(timestep.alpha(30.), 1.);
+}
+
+#[test]
+fn test_hex_color()
+{
+	let black = Color::from_rgb_f(0., 0., 0.);
+	let white = Color::from_rgb_f(1., 1., 1.);
+	let translucent = Color::from_rgba(255, 128, 0, 64);
+	assert_eq!(color_to_hex(black), "#000000");
+	assert_eq!(color_to_hex(white), "#FFFFFF");
+	assert_eq!(color_to_hex(translucent), "#FF800040");
+	for color in [black, white, translucent]
+	{
+		assert_eq!(parse_hex_color(&color_to_hex(color)), Some(color));
+	}
+	assert_eq!(parse_hex_color("fff"), Some(white));
+	assert_eq!(parse_hex_color("#00000"), None);
+	assert_eq!(parse_hex_color("#GGGGGG"), None);
+
+	#[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+	struct Test
+	{
+		#[serde(with = "hex_color")]
+		color: Color,
+	}
+	let element = to_element(&Test { color: translucent }).unwrap();
+	let test = from_element::<Test>(&element, None).unwrap();
+	assert_eq!(test.color, translucent);
+}